@@ -27,7 +27,7 @@ use simplelog::{SimpleLogger, LogLevelFilter, Config};
 use std::str;
 
 // internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
 
 fn make_population<'a>(count: u32, config: &OCRConfig<'a>) -> Vec<OCRItem<'a>> {
     let mut result = Vec::new();
@@ -92,8 +92,9 @@ struct OCRItem<'a> {
 }
 
 impl<'a> Individual for OCRItem<'a> {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    type Fitness = f64;
+
+    fn mutate(&mut self, rng: &mut DarwinRng) {
 
         let content_line = rng.gen_range(0, self.content.len());
 
@@ -130,7 +131,7 @@ impl<'a> Individual for OCRItem<'a> {
         root_mean_squared_error(&self.config.original_img, &constructed_img)
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         self.content = vec![
         TextBox{ x: 10, y: 10, text: vec![65, 65, 65, 65, 65, 65, 65, 65, 65] },
         TextBox{ x: 10, y: 40, text: vec![65, 65, 65, 65, 65, 65, 65, 65, 65] }];
@@ -208,6 +209,8 @@ fn main() {
         .threads(16)
         .add_multiple_populations(make_all_populations(16, &ocr_config, num_populations as u32))
         .share_fittest()
+        // The rendering time differs between the populations, so don't wait for the slowest one.
+        .asynchronous()
         .finalize();
 
     match ocr_builder {
@@ -27,7 +27,7 @@ use simplelog::{SimpleLogger, LogLevelFilter, Config};
 use std::str;
 
 // internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, Population, PopulationBuilder, OperatorSelection, simulation_builder};
 
 const MIN_ASCII: u8 = 32;
 const MAX_ASCII: u8 = 126;
@@ -60,7 +60,8 @@ fn make_all_populations1<'a>(individuals: u32, config: &OCRConfig<'a>, populatio
         let mut pop = PopulationBuilder::<OCRItem>::new()
             .set_id(i)
             .initial_population(&initial_population)
-            .increasing_exp_mutation_rate(1.01);
+            .increasing_exp_mutation_rate(1.01)
+            .operator_selection(OperatorSelection::AdaptivePursuit{ min_probability: 0.02, adaptation_rate: 0.3, learning_rate: 0.1 });
 
         if i == populations {
             // Special case for the last popilation
@@ -126,12 +127,22 @@ struct OCRItem<'a> {
 }
 
 impl<'a> Individual for OCRItem<'a> {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    type Fitness = f64;
 
-        let content_line = rng.gen_range(0, self.content.len());
+    fn mutate(&mut self, rng: &mut DarwinRng) {
+        let operation = rng.gen_range(0, self.operators().len());
+        self.apply_operator(operation, rng);
+    }
 
-        let operation = rng.gen_range(0, 11);
+    fn operators(&self) -> &'static [&'static str] {
+        &["change character", "swap characters", "add character", "remove character",
+          "add blank and hyphen", "new position", "move", "rotate", "change shape",
+          "add character and move left", "remove character and move right"]
+    }
+
+    fn apply_operator(&mut self, operation: usize, rng: &mut DarwinRng) {
+
+        let content_line = rng.gen_range(0, self.content.len());
 
         let index1 = rng.gen_range(0, self.content[content_line].text.len());
 
@@ -258,7 +269,7 @@ impl<'a> Individual for OCRItem<'a> {
                     }
                 }
             }
-            n => info!("mutate(): unknown operation: {}", n)
+            n => info!("apply_operator(): unknown operation: {}", n)
         }
     }
 
@@ -276,7 +287,7 @@ impl<'a> Individual for OCRItem<'a> {
         root_mean_squared_error(&self.config.original_img, &constructed_img)
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         self.content = vec![
         TextBox{ x: 0, y: 0, text: vec![65] },
         TextBox{ x: 0, y: 0, text: vec![65] }];
@@ -379,10 +390,17 @@ fn main() {
             println!("number of iterations: {}", ocr_simulation.simulation_result.iteration_counter);
             println!("Population statistics:");
 
-            for pop in ocr_simulation.habitat {
+            for pop in &ocr_simulation.habitat {
                 println!("id: {}, counter: {}", pop.id, pop.fitness_counter);
             }
 
+            println!("Operator statistics of population 1:");
+
+            for operator in &ocr_simulation.habitat[0].operator_statistics {
+                println!("{}: applications: {}, successes: {}, probability: {:.3}", operator.name,
+                    operator.num_of_applications, operator.num_of_successes, operator.probability);
+            }
+
             let ref item = ocr_simulation.simulation_result.fittest[0].individual;
             let line1 = str::from_utf8(&item.content[0].text).unwrap();
             let line2 = str::from_utf8(&item.content[1].text).unwrap();
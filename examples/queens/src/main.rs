@@ -12,7 +12,7 @@ use rand::Rng;
 use simplelog::{SimpleLogger, LogLevelFilter, Config};
 
 // internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
 
 #[derive(Debug, Clone)]
 struct Queens {
@@ -120,8 +120,10 @@ fn make_all_populations(individuals: u32, populations: u32) -> Vec<Population<Qu
 
 // implement trait functions mutate and calculate_fitness:
 impl Individual for Queens {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    // The fitness is the number of collisions
+    type Fitness = u32;
+
+    fn mutate(&mut self, rng: &mut DarwinRng) {
 
         let mut index1: usize = rng.gen_range(0, self.board.len());
         let mut index2: usize = rng.gen_range(0, self.board.len());
@@ -142,7 +144,7 @@ impl Individual for Queens {
     }
 
     // fitness means here: how many queens are colliding
-    fn calculate_fitness(&mut self) -> f64 {
+    fn calculate_fitness(&mut self) -> u32 {
         let mut num_of_collisions = 0;
 
         for row in 0..8 {
@@ -154,10 +156,10 @@ impl Individual for Queens {
             }
         }
 
-        num_of_collisions as f64
+        num_of_collisions as u32
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         self.board = vec![
             1,1,1,1,1,1,1,1,
             0,0,0,0,0,0,0,0,
@@ -177,8 +179,10 @@ fn main() {
     let _ = SimpleLogger::init(LogLevelFilter::Info, Config::default());
 
     let queens = SimulationBuilder::<Queens>::new()
-        .fitness(0.0)
+        .fitness(0)
         .threads(2)
+        // The fitness is cheap to calculate, so synchronize the populations less often.
+        .iterations_per_epoch(10)
         .add_multiple_populations(make_all_populations(100, 8))
         .finalize();
 
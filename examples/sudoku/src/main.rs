@@ -13,12 +13,12 @@ use rand::Rng;
 use simplelog::{SimpleLogger, LogLevelFilter, Config};
 
 // internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
 
 // A cell is a 3x3 sub field inside the 9x9 sudoku field
-fn fitness_of_one_cell(sudoku: &[u8], row: usize, col: usize) -> f64 {
+fn fitness_of_one_cell(sudoku: &[u8], row: usize, col: usize) -> u32 {
     let mut number_occurence = vec![0, 0, 0, 0, 0, 0, 0, 0, 0];
-    let mut error = 0.0;
+    let mut error = 0;
 
     for i in 0..3 {
         for j in 0..3 {
@@ -27,60 +27,60 @@ fn fitness_of_one_cell(sudoku: &[u8], row: usize, col: usize) -> f64 {
             if data > 0 && data < 10 {
                 number_occurence[(data - 1) as usize] += 1;
             } else {
-                error += 1.0;
+                error += 1;
             }
         }
     }
 
     for number in number_occurence {
         if number != 1 {
-            error += 1.0;
+            error += 1;
         }
     }
 
     error
 }
 
-fn fitness_of_one_row(sudoku: &[u8], row: usize) -> f64 {
+fn fitness_of_one_row(sudoku: &[u8], row: usize) -> u32 {
     let mut number_occurence = vec![0, 0, 0, 0, 0, 0, 0, 0, 0];
-    let mut error = 0.0;
+    let mut error = 0;
 
     for col in 0..9 {
         let number = sudoku[(row * 9) + col];
         if number > 0 && number < 10 {
             number_occurence[(number - 1) as usize] += 1;
         } else {
-            error += 1.0;
+            error += 1;
         }
     }
 
     // Each number must be unique, otherwise increase error
     for number in number_occurence {
         if number != 1 {
-            error += 1.0;
+            error += 1;
         }
     }
 
     error
 }
 
-fn fitness_of_one_col(sudoku: &[u8], col: usize) -> f64 {
+fn fitness_of_one_col(sudoku: &[u8], col: usize) -> u32 {
     let mut number_occurence = vec![0, 0, 0, 0, 0, 0, 0, 0, 0];
-    let mut error = 0.0;
+    let mut error = 0;
 
     for row in 0..9 {
         let number = sudoku[(row * 9) + col];
         if number > 0 && number < 10 {
             number_occurence[(number - 1) as usize] += 1;
         } else {
-            error += 1.0;
+            error += 1;
         }
     }
 
     // Each number must be unique, otherwise increase error
     for number in number_occurence {
         if number != 1 {
-            error += 1.0;
+            error += 1;
         }
     }
 
@@ -139,9 +139,10 @@ struct Sudoku {
 
 // implement trait functions mutate and calculate_fitness:
 impl Individual for Sudoku {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    // The fitness is the number of errors
+    type Fitness = u32;
 
+    fn mutate(&mut self, rng: &mut DarwinRng) {
         let mut index: usize = rng.gen_range(0, self.solved.len());
 
         // pick free (= not pre set) position
@@ -154,8 +155,8 @@ impl Individual for Sudoku {
     }
 
     // fitness means here: how many errors
-    fn calculate_fitness(&mut self) -> f64 {
-        let mut result = 0.0;
+    fn calculate_fitness(&mut self) -> u32 {
+        let mut result = 0;
 
         for i in 0..9 {
             result += fitness_of_one_row(&self.solved, i);
@@ -182,7 +183,27 @@ impl Individual for Sudoku {
         result
     }
 
-    fn reset(&mut self) {
+    // The given numbers are hard constraints: count the free positions that repeat a given
+    // number in the same row, column or cell. All other duplicate numbers (fitness) are soft
+    // constraints. An individual that clashes with a given number is always worse than one
+    // that doesn't (feasibility rules, the default constraint handling).
+    fn constraint_violation(&self) -> f64 {
+        let clashes = |index: usize| {
+            let (row, col) = (index / 9, index % 9);
+
+            (0..81).any(|other| {
+                let (other_row, other_col) = (other / 9, other % 9);
+                let same_cell = (row / 3 == other_row / 3) && (col / 3 == other_col / 3);
+
+                self.unsolved[other] != 0 && self.unsolved[other] == self.solved[index] &&
+                    (row == other_row || col == other_col || same_cell)
+            })
+        };
+
+        (0..81).filter(|index| self.unsolved[*index] == 0 && clashes(*index)).count() as f64
+    }
+
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         self.solved = (*self.unsolved).clone();
     }
 }
@@ -193,7 +214,7 @@ fn main() {
     let _ = SimpleLogger::init(LogLevelFilter::Info, Config::default());
 
     let sudoku = SimulationBuilder::<Sudoku>::new()
-        .fitness(0.0)
+        .fitness(0)
         .threads(4)
         .add_multiple_populations(make_all_populations(100, 16))
         .finalize();
@@ -12,11 +12,12 @@ extern crate simplelog;
 extern crate darwin_rs;
 
 use std::sync::Arc;
+use std::time::Duration;
 use rand::Rng;
 use simplelog::{SimpleLogger, LogLevelFilter, Config};
 
 // Internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, SimulationType, Population, PopulationBuilder, simulation_builder};
 
 fn city_distance(city: &[(f64, f64)], index1: usize, index2: usize) -> f64 {
     let (x1, y1) = city[index1];
@@ -75,8 +76,9 @@ struct CityItem {
 
 // Implement trait functions mutate and calculate_fitness:
 impl Individual for CityItem {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    type Fitness = f64;
+
+    fn mutate(&mut self, rng: &mut DarwinRng) {
         // Keep stating position always the same: (random numbers from 1, not 0)
         let index1: usize = rng.gen_range(1, self.cities.len());
         let mut index2: usize = rng.gen_range(1, self.cities.len());
@@ -109,7 +111,7 @@ impl Individual for CityItem {
         length
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         let mut path: Vec<usize> = (0..self.cities.len()).map(|x| x as usize).collect();
         path.push(0); // Add start position to end of path
 
@@ -145,7 +147,11 @@ fn main() {
 
     let tsp = SimulationBuilder::<CityItem>::new()
         // .factor(0.34)
-        .fitness(459.0)
+        // Stop at the given fitness, but after one minute at the latest.
+        .termination(SimulationType::AnyOf(vec![
+            SimulationType::EndFitness(459.0),
+            SimulationType::EndTime(Duration::from_secs(60)),
+        ]))
         .threads(4)
         .add_multiple_populations(make_all_populations(100, 8, &cities))
         .finalize();
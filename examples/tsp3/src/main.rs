@@ -21,7 +21,7 @@ use std::io::BufReader;
 use std::io::prelude::*;
 
 // Internal modules
-use darwin_rs::{Individual, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
+use darwin_rs::{Individual, DarwinRng, SimulationBuilder, Population, PopulationBuilder, simulation_builder};
 
 fn city_distance(city: &[(f64, f64)], index1: usize, index2: usize) -> f64 {
     let (x1, y1) = city[index1];
@@ -82,8 +82,9 @@ struct CityItem {
 
 // Implement trait functions mutate and calculate_fitness:
 impl Individual for CityItem {
-    fn mutate(&mut self) {
-        let mut rng = rand::thread_rng();
+    type Fitness = f64;
+
+    fn mutate(&mut self, rng: &mut DarwinRng) {
         // Keep stating position always the same: (random numbers from 1, not 0)
         let index1: usize = rng.gen_range(1, self.cities.len());
         let mut index2: usize = rng.gen_range(1, self.cities.len());
@@ -138,7 +139,7 @@ impl Individual for CityItem {
         length
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, _rng: &mut DarwinRng) {
         let mut path: Vec<usize> = (0..self.cities.len()).map(|x| x as usize).collect();
         path.push(0); // Add start position to end of path
 
@@ -0,0 +1,323 @@
+//! This module defines the `Executor` trait and the executors that run the jobs of an
+//! iteration, for example the populations, sequentially or on a thread pool.
+
+use std::any::Any;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use jobsteal::{make_pool, Pool};
+
+/// A job for an executor. It may borrow data from the simulation, the executor has to finish
+/// it before `execute` returns.
+pub type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// This trait has to be implemented for an executor.
+/// Use the `executor` method of the `SimulationBuilder` to set it. Since an executor is shared
+/// through an `Arc`, one executor (and its threads) can be used by several simulations.
+/// The built-in executors are `Sequential`, `JobstealPool` and `SharedPool`, the default is a
+/// `JobstealPool` with the number of threads given by `threads` in the `SimulationBuilder`.
+pub trait Executor: Send + Sync {
+    /// This method runs all the given jobs, possibly in parallel, and returns when all of them
+    /// have finished. The jobs do not depend on each other.
+    fn execute(&self, jobs: Vec<Job>);
+
+    /// The number of jobs that can run in parallel. The `Automatic` scheduling of the simulation
+    /// uses this to decide how to split up the work.
+    fn num_of_threads(&self) -> usize;
+}
+
+/// Runs the jobs one after another on the calling thread. This is useful for debugging and
+/// profiling. The result of a simulation with a seed is the same as with any other executor.
+#[derive(Debug,Clone)]
+pub struct Sequential;
+
+impl Executor for Sequential {
+    fn execute(&self, jobs: Vec<Job>) {
+        for job in jobs {
+            job();
+        }
+    }
+
+    fn num_of_threads(&self) -> usize {
+        1
+    }
+}
+
+/// The payload of the first job that has panicked, it is passed on to the caller of `execute`.
+type Panic = Mutex<Option<Box<dyn Any + Send>>>;
+
+/// Locks the mutex, even if another thread has panicked while holding it.
+fn lock<'a, T>(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs the job and catches its panic, so that the thread pool is not affected by it.
+fn run_job(job: Job, panic: &Panic) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(job)) {
+        lock(panic).get_or_insert(payload);
+    }
+}
+
+/// Passes the panic of a job on to the caller of `execute`, after all jobs have finished.
+fn resume_panic(panic: &Panic) {
+    if let Some(payload) = lock(panic).take() {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Runs the jobs on a work stealing thread pool (jobsteal crate). The pool can only run the
+/// jobs of one simulation at a time, if it is shared the other simulations wait until it is
+/// free. Use a `SharedPool` for several simulations that should run at the same time.
+pub struct JobstealPool {
+    /// The actual pool, it needs mutable access to run jobs.
+    pool: Mutex<Pool>,
+    /// The number of threads of the pool.
+    num_of_threads: usize,
+}
+
+impl JobstealPool {
+    /// Creates a new thread pool with the given number of threads.
+    pub fn new(num_of_threads: usize) -> JobstealPool {
+        JobstealPool {
+            pool: Mutex::new(make_pool(num_of_threads).unwrap()),
+            num_of_threads,
+        }
+    }
+}
+
+impl Executor for JobstealPool {
+    fn execute(&self, jobs: Vec<Job>) {
+        let panic = Mutex::new(None);
+
+        // A panicking job would take down a worker thread of the pool, so the panics are
+        // caught in the jobs and the lock is released before the panic is passed on.
+        {
+            let mut pool = self.pool.lock().unwrap();
+            let panic = &panic;
+
+            pool.scope(|scope|
+                for job in jobs {
+                    scope.submit(move || run_job(job, panic));
+                });
+        }
+
+        resume_panic(&panic);
+    }
+
+    fn num_of_threads(&self) -> usize {
+        self.num_of_threads
+    }
+}
+
+/// A job of a `SharedPool`, see `SharedPool::execute`.
+type StaticJob = Box<dyn FnOnce() + Send + 'static>;
+
+/// The jobs of one call to `SharedPool::execute` that have not finished yet.
+struct Batch {
+    remaining: Mutex<usize>,
+    finished: Condvar,
+    panic: Panic,
+}
+
+impl Batch {
+    /// Called by each job when it has finished.
+    fn finish_job(&self) {
+        let mut remaining = lock(&self.remaining);
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.finished.notify_all();
+        }
+    }
+}
+
+/// Blocks until all jobs of the batch have finished when it is dropped, so also if `execute`
+/// unwinds.
+struct WaitForBatch<'a>(&'a Batch);
+
+impl<'a> Drop for WaitForBatch<'a> {
+    fn drop(&mut self) {
+        let mut remaining = lock(&self.0.remaining);
+        while *remaining > 0 {
+            remaining = self.0.finished.wait(remaining).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// Runs the jobs on a fixed number of threads that take them from a channel. Unlike the
+/// `JobstealPool`, the jobs of several simulations that share this pool run at the same time,
+/// the threads are never used by more than one job. A job must not call `execute` of the same
+/// pool, since it would wait for a free thread that it occupies itself.
+pub struct SharedPool {
+    /// The channel to the threads, it is closed when the pool is dropped.
+    sender: Mutex<Option<Sender<StaticJob>>>,
+    /// The threads of the pool.
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl SharedPool {
+    /// Creates a new thread pool with the given number of threads (at least one).
+    pub fn new(num_of_threads: usize) -> SharedPool {
+        let (sender, receiver) = mpsc::channel::<StaticJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..num_of_threads.max(1)).map(|_| {
+            let receiver = receiver.clone();
+
+            thread::spawn(move || loop {
+                // The jobs do not panic, so the lock is only held while waiting for the next job.
+                let job = receiver.lock().unwrap().recv();
+
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => break,
+                }
+            })
+        }).collect();
+
+        SharedPool {
+            sender: Mutex::new(Some(sender)),
+            workers,
+        }
+    }
+}
+
+impl Executor for SharedPool {
+    fn execute(&self, jobs: Vec<Job>) {
+        let batch = Arc::new(Batch {
+            remaining: Mutex::new(0),
+            finished: Condvar::new(),
+            panic: Mutex::new(None),
+        });
+        let wait = WaitForBatch(&batch);
+
+        {
+            let sender = lock(&self.sender);
+
+            for job in jobs {
+                *lock(&batch.remaining) += 1;
+
+                // SAFETY: the job may borrow data that only lives as long as this call of
+                // `execute`. It is counted in `remaining` before it is sent and `wait` blocks
+                // until `remaining` is 0 again, also if `execute` unwinds. Nothing panics while
+                // the job is pending: the locks ignore poisoning and `run_job` catches the panic
+                // of the job. So no thread uses the borrowed data after `execute` has returned.
+                let job = unsafe { mem::transmute::<Job, StaticJob>(job) };
+                let batch = batch.clone();
+
+                let job: StaticJob = Box::new(move || {
+                    run_job(job, &batch.panic);
+                    batch.finish_job();
+                });
+
+                // The threads only stop when the pool is dropped, just in case run the job here.
+                match *sender {
+                    Some(ref sender) => if let Err(mpsc::SendError(job)) = sender.send(job) {
+                        job();
+                    },
+                    None => job(),
+                }
+            }
+        }
+
+        drop(wait);
+        resume_panic(&batch.panic);
+    }
+
+    fn num_of_threads(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for SharedPool {
+    fn drop(&mut self) {
+        // Close the channel, then the threads stop after their current job.
+        lock(&self.sender).take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{Executor, Job, JobstealPool, SharedPool, Sequential};
+
+    fn sum_with(executor: &dyn Executor) -> Vec<usize> {
+        let mut results = vec![0; 10];
+        let counter = AtomicUsize::new(0);
+
+        {
+            let counter = &counter;
+            let jobs: Vec<Job> = results.iter_mut().enumerate().map(|(index, result)| Box::new(move || {
+                *result = index * index;
+                counter.fetch_add(1, Ordering::SeqCst);
+            }) as Job).collect();
+
+            executor.execute(jobs);
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+        results
+    }
+
+    #[test]
+    fn execute1() {
+        let expected: Vec<usize> = (0..10).map(|index| index * index).collect();
+        assert_eq!(sum_with(&Sequential), expected);
+        assert_eq!(sum_with(&JobstealPool::new(3)), expected);
+        assert_eq!(sum_with(&SharedPool::new(3)), expected);
+    }
+
+    #[test]
+    fn panic1() {
+        let executors: Vec<Box<dyn Executor>> = vec![Box::new(JobstealPool::new(2)), Box::new(SharedPool::new(2))];
+
+        for executor in executors {
+            let jobs: Vec<Job> = vec![Box::new(|| panic!("job blew up")), Box::new(|| {})];
+            assert!(panic::catch_unwind(AssertUnwindSafe(|| executor.execute(jobs))).is_err());
+
+            // The pool is still usable.
+            assert_eq!(sum_with(&*executor)[3], 9);
+        }
+    }
+
+    #[test]
+    fn shared1() {
+        // Each job waits for the job of the other caller, this only works if both run at
+        // the same time.
+        let executor = Arc::new(SharedPool::new(2));
+        let (sender1, receiver1) = mpsc::channel();
+        let (sender2, receiver2) = mpsc::channel();
+
+        let handles: Vec<_> = vec![(sender1, receiver2), (sender2, receiver1)].into_iter().map(|(sender, receiver)| {
+            let executor = executor.clone();
+
+            thread::spawn(move || {
+                let mut received = false;
+                {
+                    let received = &mut received;
+                    executor.execute(vec![Box::new(move || {
+                        sender.send(()).unwrap();
+                        *received = receiver.recv_timeout(Duration::from_secs(10)).is_ok();
+                    })]);
+                }
+                received
+            })
+        }).collect();
+
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+    }
+}
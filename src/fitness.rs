@@ -0,0 +1,513 @@
+//! This module defines the `Fitness` trait for the fitness type of an individual and how a
+//! fitness is written to a checkpoint.
+
+use std::fmt::Debug;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, DeserializeOwned};
+use serde::ser;
+
+/// This trait has to be implemented for the fitness type of an individual
+/// (see `Fitness` in the `Individual` trait).
+/// It is already implemented for all the primitive number types and for tuples of two or three
+/// fitness values, which are compared lexicographically. For example `(hard_violations, soft_cost)`
+/// compares the number of hard violations first and only if these are equal the soft cost.
+/// A user defined fitness type also needs `Serialize` and `Deserialize` (serde), if the
+/// simulation is saved to a checkpoint.
+pub trait Fitness: Clone + PartialOrd + Debug + Default + Send + Sync {
+    /// Converts the fitness into a floating point number. This is used to calculate the
+    /// improvement factor and for the fitness proportional selection strategies.
+    /// For tuples only the first value is used.
+    fn to_f64(&self) -> f64;
+    /// Returns true if the fitness can not be compared to itself, for example if it is NaN or
+    /// a tuple containing NaN. Such a fitness is always treated as the worst fitness.
+    fn is_nan(&self) -> bool {
+        self.partial_cmp(self).is_none()
+    }
+}
+
+macro_rules! impl_fitness {
+    ($($t:ty),*) => {
+        $(
+            impl Fitness for $t {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+            }
+        )*
+    }
+}
+
+impl_fitness!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl<A: Fitness, B: Fitness> Fitness for (A, B) {
+    fn to_f64(&self) -> f64 {
+        self.0.to_f64()
+    }
+}
+
+impl<A: Fitness, B: Fitness, C: Fitness> Fitness for (A, B, C) {
+    fn to_f64(&self) -> f64 {
+        self.0.to_f64()
+    }
+}
+
+/// Returns the text that is written to a checkpoint for NaN and infinity, since JSON has no
+/// such numbers.
+fn float_to_text(value: f64) -> Option<&'static str> {
+    if value.is_nan() {
+        Some("NaN")
+    } else if value.is_infinite() {
+        Some(if value > 0.0 { "inf" } else { "-inf" })
+    } else {
+        None
+    }
+}
+
+/// The reverse of `float_to_text`.
+fn text_to_float(text: &str) -> Option<f64> {
+    match text {
+        "NaN" => Some(f64::NAN),
+        "inf" => Some(f64::INFINITY),
+        "-inf" => Some(f64::NEG_INFINITY),
+        _ => None,
+    }
+}
+
+/// Writes a fitness to a checkpoint (use with `#[serde(with = "...")]`). Every floating point
+/// number in the fitness, also the ones inside a tuple or a user defined type, is written like
+/// `checkpoint_f64` does, so NaN and infinity can be restored. Only the payload of an enum is
+/// written unchanged.
+pub(crate) mod checkpoint_fitness {
+    use super::*;
+
+    use std::vec;
+
+    use serde::de::{Visitor, SeqAccess, MapAccess, DeserializeSeed};
+    use serde::ser::{SerializeSeq, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+        SerializeMap, SerializeStruct, SerializeStructVariant};
+    use serde_json::{self, Value, Map};
+    use serde_json::value::Serializer as ValueSerializer;
+
+    pub fn serialize<F: Serialize, S: Serializer>(fitness: &F, serializer: S) -> Result<S::Ok, S::Error> {
+        Floats(fitness).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, F: DeserializeOwned, D: Deserializer<'de>>(deserializer: D) -> Result<F, D::Error> {
+        F::deserialize(FloatDeserializer(Value::deserialize(deserializer)?)).map_err(de::Error::custom)
+    }
+
+    /// Serializes a value with all its floating point numbers written as in `checkpoint_f64`.
+    struct Floats<'a, T: ?Sized + 'a>(&'a T);
+
+    impl<'a, T: ?Sized + Serialize> Serialize for Floats<'a, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(FloatSerializer).map_err(ser::Error::custom)?.serialize(serializer)
+        }
+    }
+
+    /// Converts a value into JSON like `serde_json::value::Serializer`, but writes NaN and
+    /// infinity as text.
+    struct FloatSerializer;
+
+    /// The elements of a sequence, map or struct, each of them is written with `Floats`.
+    struct Compound<C>(C);
+
+    type Error = serde_json::Error;
+
+    impl ser::Serializer for FloatSerializer {
+        type Ok = Value;
+        type Error = Error;
+        type SerializeSeq = Compound<<ValueSerializer as ser::Serializer>::SerializeSeq>;
+        type SerializeTuple = Compound<<ValueSerializer as ser::Serializer>::SerializeTuple>;
+        type SerializeTupleStruct = Compound<<ValueSerializer as ser::Serializer>::SerializeTupleStruct>;
+        type SerializeTupleVariant = Compound<<ValueSerializer as ser::Serializer>::SerializeTupleVariant>;
+        type SerializeMap = Compound<<ValueSerializer as ser::Serializer>::SerializeMap>;
+        type SerializeStruct = Compound<<ValueSerializer as ser::Serializer>::SerializeStruct>;
+        type SerializeStructVariant = Compound<<ValueSerializer as ser::Serializer>::SerializeStructVariant>;
+
+        fn serialize_bool(self, value: bool) -> Result<Value, Error> { ValueSerializer.serialize_bool(value) }
+        fn serialize_i8(self, value: i8) -> Result<Value, Error> { ValueSerializer.serialize_i8(value) }
+        fn serialize_i16(self, value: i16) -> Result<Value, Error> { ValueSerializer.serialize_i16(value) }
+        fn serialize_i32(self, value: i32) -> Result<Value, Error> { ValueSerializer.serialize_i32(value) }
+        fn serialize_i64(self, value: i64) -> Result<Value, Error> { ValueSerializer.serialize_i64(value) }
+        fn serialize_u8(self, value: u8) -> Result<Value, Error> { ValueSerializer.serialize_u8(value) }
+        fn serialize_u16(self, value: u16) -> Result<Value, Error> { ValueSerializer.serialize_u16(value) }
+        fn serialize_u32(self, value: u32) -> Result<Value, Error> { ValueSerializer.serialize_u32(value) }
+        fn serialize_u64(self, value: u64) -> Result<Value, Error> { ValueSerializer.serialize_u64(value) }
+        fn serialize_char(self, value: char) -> Result<Value, Error> { ValueSerializer.serialize_char(value) }
+        fn serialize_str(self, value: &str) -> Result<Value, Error> { ValueSerializer.serialize_str(value) }
+        fn serialize_bytes(self, value: &[u8]) -> Result<Value, Error> { ValueSerializer.serialize_bytes(value) }
+        fn serialize_none(self) -> Result<Value, Error> { ValueSerializer.serialize_none() }
+        fn serialize_unit(self) -> Result<Value, Error> { ValueSerializer.serialize_unit() }
+
+        fn serialize_f32(self, value: f32) -> Result<Value, Error> {
+            self.serialize_f64(value as f64)
+        }
+
+        fn serialize_f64(self, value: f64) -> Result<Value, Error> {
+            match float_to_text(value) {
+                Some(text) => Ok(Value::String(text.to_string())),
+                None => ValueSerializer.serialize_f64(value),
+            }
+        }
+
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_unit_struct(self, name: &'static str) -> Result<Value, Error> {
+            ValueSerializer.serialize_unit_struct(name)
+        }
+
+        fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<Value, Error> {
+            ValueSerializer.serialize_unit_variant(name, index, variant)
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32,
+            variant: &'static str, value: &T) -> Result<Value, Error> {
+            let mut map = Map::new();
+            map.insert(variant.to_string(), value.serialize(self)?);
+            Ok(Value::Object(map))
+        }
+
+        fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            ValueSerializer.serialize_seq(len).map(Compound)
+        }
+
+        fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+            ValueSerializer.serialize_tuple(len).map(Compound)
+        }
+
+        fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+            ValueSerializer.serialize_tuple_struct(name, len).map(Compound)
+        }
+
+        fn serialize_tuple_variant(self, name: &'static str, index: u32, variant: &'static str,
+            len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+            ValueSerializer.serialize_tuple_variant(name, index, variant, len).map(Compound)
+        }
+
+        fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            ValueSerializer.serialize_map(len).map(Compound)
+        }
+
+        fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+            ValueSerializer.serialize_struct(name, len).map(Compound)
+        }
+
+        fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str,
+            len: usize) -> Result<Self::SerializeStructVariant, Error> {
+            ValueSerializer.serialize_struct_variant(name, index, variant, len).map(Compound)
+        }
+    }
+
+    impl<C: SerializeSeq<Ok = Value, Error = Error>> SerializeSeq for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.serialize_element(&Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeTuple<Ok = Value, Error = Error>> SerializeTuple for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.serialize_element(&Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeTupleStruct<Ok = Value, Error = Error>> SerializeTupleStruct for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.serialize_field(&Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeTupleVariant<Ok = Value, Error = Error>> SerializeTupleVariant for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.serialize_field(&Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeMap<Ok = Value, Error = Error>> SerializeMap for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+            self.0.serialize_key(key)
+        }
+
+        fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.serialize_value(&Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeStruct<Ok = Value, Error = Error>> SerializeStruct for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+            self.0.serialize_field(key, &Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: SerializeStructVariant<Ok = Value, Error = Error>> SerializeStructVariant for Compound<C> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+            self.0.serialize_field(key, &Floats(value))
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            self.0.end()
+        }
+    }
+
+    /// Reads a value that has been written with `FloatSerializer`. The text for NaN and
+    /// infinity and also `null` are accepted where a floating point number is expected.
+    struct FloatDeserializer(Value);
+
+    impl<'de> Deserializer<'de> for FloatDeserializer {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.0 {
+                Value::Array(values) => visitor.visit_seq(FloatSeq(values.into_iter())),
+                Value::Object(map) => visitor.visit_map(FloatMap { entries: map.into_iter(), value: None }),
+                value => value.deserialize_any(visitor),
+            }
+        }
+
+        fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.deserialize_f64(visitor)
+        }
+
+        fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let value = match self.0 {
+                Value::Null => Some(f64::NAN),
+                Value::String(ref text) => text_to_float(text),
+                _ => None,
+            };
+
+            match value {
+                Some(value) => visitor.visit_f64(value),
+                None => self.0.deserialize_f64(visitor),
+            }
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.0 {
+                Value::Null => visitor.visit_none(),
+                _ => visitor.visit_some(self),
+            }
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+            visitor: V) -> Result<V::Value, Error> {
+            self.0.deserialize_enum(name, variants, visitor)
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf unit
+            unit_struct seq tuple tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    /// The elements of an array, each of them is read with `FloatDeserializer`.
+    struct FloatSeq(vec::IntoIter<Value>);
+
+    impl<'de> SeqAccess<'de> for FloatSeq {
+        type Error = Error;
+
+        fn next_element_seed<E: DeserializeSeed<'de>>(&mut self, seed: E) -> Result<Option<E::Value>, Error> {
+            match self.0.next() {
+                Some(value) => seed.deserialize(FloatDeserializer(value)).map(Some),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// The entries of an object, each value is read with `FloatDeserializer`.
+    struct FloatMap {
+        entries: serde_json::map::IntoIter,
+        value: Option<Value>,
+    }
+
+    impl<'de> MapAccess<'de> for FloatMap {
+        type Error = Error;
+
+        fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+            match self.entries.next() {
+                Some((key, value)) => {
+                    self.value = Some(value);
+                    seed.deserialize(Value::String(key)).map(Some)
+                }
+                None => Ok(None),
+            }
+        }
+
+        fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+            match self.value.take() {
+                Some(value) => seed.deserialize(FloatDeserializer(value)),
+                None => Err(de::Error::custom("value is missing")),
+            }
+        }
+    }
+}
+
+/// Writes a floating point number to a checkpoint (use with `#[serde(with = "...")]`).
+/// JSON has no NaN and infinity, so these are written as the strings "NaN", "inf" and "-inf".
+/// `null` is read as NaN.
+pub(crate) mod checkpoint_f64 {
+    use super::*;
+
+    /// A floating point number as it is written to a checkpoint.
+    #[derive(Serialize,Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Number(f64),
+        Text(String),
+    }
+
+    fn to_value(value: f64) -> Value {
+        match float_to_text(value) {
+            Some(text) => Value::Text(text.to_string()),
+            None => Value::Number(value),
+        }
+    }
+
+    fn from_value<E: de::Error>(value: Option<Value>) -> Result<f64, E> {
+        match value {
+            None => Ok(f64::NAN),
+            Some(Value::Number(value)) => Ok(value),
+            Some(Value::Text(text)) => text_to_float(&text)
+                .ok_or_else(|| E::custom(format!("invalid floating point number: {}", text))),
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        to_value(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        from_value(Option::<Value>::deserialize(deserializer)?)
+    }
+
+    /// The same for a vector of floating point numbers.
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(values.iter().map(|value| to_value(*value)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+            Vec::<Option<Value>>::deserialize(deserializer)?.into_iter().map(from_value).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json;
+
+    use super::Fitness;
+
+    #[derive(Debug,Serialize,Deserialize)]
+    struct Checkpoint {
+        #[serde(with = "::fitness::checkpoint_fitness")]
+        fitness: (u32, f64),
+        #[serde(with = "::fitness::checkpoint_fitness")]
+        values: Vec<f32>,
+    }
+
+    #[test]
+    fn tuple1() {
+        assert!((1u32, 5.0) < (2u32, 0.0));
+        assert!((1u32, 0.5) < (1u32, 2.0));
+        assert_eq!((3u32, 5.0).to_f64(), 3.0);
+    }
+
+    #[test]
+    fn is_nan1() {
+        assert!(Fitness::is_nan(&f64::NAN));
+        assert!(Fitness::is_nan(&(1u32, f64::NAN)));
+        assert!(!Fitness::is_nan(&(1u32, 2.0)));
+        assert!(!Fitness::is_nan(&5u32));
+    }
+
+    #[test]
+    fn checkpoint1() {
+        let checkpoint = Checkpoint {
+            fitness: (5, f64::NAN),
+            values: vec![1.5, f32::INFINITY, f32::NEG_INFINITY],
+        };
+
+        let text = serde_json::to_string(&checkpoint).unwrap();
+        assert_eq!(text, r#"{"fitness":[5,"NaN"],"values":[1.5,"inf","-inf"]}"#);
+
+        let restored: Checkpoint = serde_json::from_str(&text).unwrap();
+        assert_eq!(restored.fitness.0, 5);
+        assert!(restored.fitness.1.is_nan());
+        assert_eq!(restored.values, vec![1.5, f32::INFINITY, f32::NEG_INFINITY]);
+    }
+
+    #[test]
+    fn user_defined1() {
+        // A user defined fitness type does not need serde.
+        #[derive(Debug,Clone,PartialEq,PartialOrd,Default)]
+        struct Cost(u32);
+
+        impl Fitness for Cost {
+            fn to_f64(&self) -> f64 {
+                self.0 as f64
+            }
+        }
+
+        assert!(Cost(1) < Cost(2));
+        assert!(!Cost(1).is_nan());
+        assert_eq!(Cost(3).to_f64(), 3.0);
+    }
+}
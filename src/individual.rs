@@ -15,26 +15,125 @@
 
 // external modules
 use std::cmp::Ordering;
+use std::f64;
+use std::panic::{self, AssertUnwindSafe};
+
+use fitness::Fitness;
+use random::DarwinRng;
+use simulation::Direction;
 
 /// A wrapper helper struct for the individuals.
 /// It does the book keeping of the fitness and the number of mutations this individual
 /// has to run in one iteration.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,Serialize,Deserialize)]
+#[serde(bound(serialize = "T: ::serde::Serialize, T::Fitness: ::serde::Serialize",
+    deserialize = "T: ::serde::de::DeserializeOwned, T::Fitness: ::serde::de::DeserializeOwned"))]
 pub struct IndividualWrapper<T: Individual> {
     /// The actual individual, user defined struct.
     pub individual: T,
     /// The current calculated fitness for this individual.
-    pub fitness: f64,
+    #[serde(with = "::fitness::checkpoint_fitness")]
+    pub fitness: T::Fitness,
     /// The number of mutation this individual is doing in one iteration.
     pub num_of_mutations: u32,
     /// The id of the population that this individual belongs to. Just for statistics.
     pub id: u32,
+    /// The objectives of the individual for a multi-objective optimization.
+    /// Empty if the `objectives` method of the `Individual` trait is not implemented.
+    #[serde(with = "::fitness::checkpoint_f64::vec")]
+    pub objectives: Vec<f64>,
+    /// The amount of constraint violation of the individual, 0.0 means feasible.
+    /// See the `constraint_violation` method of the `Individual` trait.
+    #[serde(with = "::fitness::checkpoint_f64")]
+    pub constraint_violation: f64,
+    /// False if the `mutate`, `crossover` or `calculate_fitness` method of the individual has
+    /// panicked. Invalid individuals are always sorted last and offspring are discarded.
+    pub valid: bool,
+}
+
+impl<T: Individual> IndividualWrapper<T> {
+    /// Repairs the wrapped individual and calculates its fitness, objectives and constraint
+    /// violation. If the calculation panics, the panic is caught and the individual is marked
+    /// as invalid.
+    pub fn calculate_fitness(&mut self) {
+        let individual = &mut self.individual;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| {
+            individual.repair();
+            (individual.calculate_fitness(), individual.objectives(), individual.constraint_violation())
+        })) {
+            Ok((fitness, objectives, constraint_violation)) => {
+                self.fitness = fitness;
+                self.objectives = objectives;
+                self.constraint_violation = constraint_violation;
+                self.valid = true;
+            }
+            Err(_) => {
+                self.valid = false;
+            }
+        }
+    }
+
+    /// Resets the wrapped individual (see the `reset` method of the `Individual` trait).
+    /// If it panics, the panic is caught, the individual is marked as invalid and false is
+    /// returned. The fitness has to be calculated again afterwards.
+    pub fn reset(&mut self, rng: &mut DarwinRng) -> bool {
+        let individual = &mut self.individual;
+
+        if panic::catch_unwind(AssertUnwindSafe(|| individual.reset(rng))).is_ok() {
+            true
+        } else {
+            self.valid = false;
+            false
+        }
+    }
+
+    /// Returns the names of the mutation operators of the wrapped individual (see the `operators`
+    /// method of the `Individual` trait). If it panics, the individual has no operators.
+    pub fn operators(&self) -> &'static [&'static str] {
+        panic::catch_unwind(AssertUnwindSafe(|| self.individual.operators())).unwrap_or(&[])
+    }
+
+    /// Returns true if the individual is invalid or its fitness or constraint violation is NaN.
+    pub fn has_failed(&self) -> bool {
+        !self.valid || self.fitness.is_nan() || self.constraint_violation.is_nan()
+    }
+
+    /// Returns true if the individual is valid and does not violate any constraint.
+    pub fn is_feasible(&self) -> bool {
+        !self.has_failed() && self.constraint_violation <= 0.0
+    }
+
+    /// Compares two individuals by Deb's feasibility rules, without looking at the fitness:
+    /// valid individuals come first, then the ones with the lower constraint violation.
+    /// Feasible individuals are therefore always better than infeasible ones.
+    pub fn compare_feasibility(&self, other: &IndividualWrapper<T>) -> Ordering {
+        let violation = |wrapper: &IndividualWrapper<T>|
+            if wrapper.constraint_violation.is_nan() { f64::INFINITY } else { wrapper.constraint_violation.max(0.0) };
+
+        (!self.valid).cmp(&!other.valid).then_with(||
+            violation(self).partial_cmp(&violation(other)).unwrap_or(Ordering::Equal))
+    }
+
+    /// Compares two individuals by their feasibility (see `compare_feasibility`) and then by
+    /// their fitness in the given direction. The better one comes first (`Ordering::Less`).
+    pub fn compare_fitness(&self, other: &IndividualWrapper<T>, direction: Direction) -> Ordering {
+        self.compare_feasibility(other).then_with(|| direction.compare(&self.fitness, &other.fitness))
+    }
+
+    /// The distance between two individuals, see the `distance` method of the `Individual` trait.
+    /// If it is not implemented or if it panics, the difference of the fitness values is used.
+    pub fn distance(&self, other: &IndividualWrapper<T>) -> f64 {
+        panic::catch_unwind(AssertUnwindSafe(|| self.individual.distance(&other.individual)))
+            .unwrap_or(None)
+            .unwrap_or_else(|| (self.fitness.to_f64() - other.fitness.to_f64()).abs())
+    }
 }
 
 /// Implement this for sorting
 impl<T: Individual> PartialEq for IndividualWrapper<T> {
     fn eq(&self, other: &IndividualWrapper<T>) -> bool {
-        self.fitness == other.fitness
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -44,22 +143,32 @@ impl<T: Individual> Eq for IndividualWrapper<T> {}
 /// Implement this for sorting
 impl<T: Individual> Ord for IndividualWrapper<T> {
     fn cmp(&self, other: &IndividualWrapper<T>) -> Ordering {
-        self.partial_cmp(other).expect("Fitness of Individual is NaN")
+        // A NaN fitness is the worst one.
+        Direction::Minimize.compare(&self.fitness, &other.fitness)
     }
 }
 
 /// Implement this for sorting
 impl<T: Individual> PartialOrd for IndividualWrapper<T> {
     fn partial_cmp(&self, other: &IndividualWrapper<T>) -> Option<Ordering> {
-        self.fitness.partial_cmp(&other.fitness)
+        Some(self.cmp(other))
     }
 }
 
 /// This trait has to be implemented for the user defined struct.
 /// In order to share common data between all individuals use Arc. See TSP and OCR exmaples.
 ///
-/// TODO: add serialization, see https://github.com/willi-kappler/darwin-rs/issues/11
+/// If the struct and its fitness type also implement `Serialize` and `Deserialize` (serde), the
+/// whole simulation can be saved to and restored from a checkpoint file.
+/// See https://github.com/willi-kappler/darwin-rs/issues/11
+///
+/// All random numbers should be drawn from the random number generator that is passed to
+/// `mutate`, `crossover` and `reset` (and not from `rand::thread_rng()`), then a simulation with
+/// a seed (see `seed` in the `SimulationBuilder`) can be reproduced exactly.
 pub trait Individual {
+    /// The type of the fitness, for example `f64` or `u32` for the number of errors, or a tuple
+    /// like `(u32, f64)` for a lexicographic comparison.
+    type Fitness: Fitness;
     /// This method mutates the individual. Usually this is a cheap and easy to implement
     /// function. In order to improve the simulation, the user can make this function a bit
     /// "smarter". This is nicely shown in the tsp and tsp2 example. The tsp2 example contains
@@ -75,17 +184,78 @@ pub trait Individual {
     /// order by just randomly swaping positions are very slim. So just start with one simple
     /// mutation function (one operation) and add more and more "smarter" mutation types to the
     /// mutate function.
-    fn mutate(&mut self);
+    fn mutate(&mut self, rng: &mut DarwinRng);
+    /// This method recombines the individual with another individual (crossover). It is called
+    /// on a copy of the first parent with the second parent as argument, before the offspring
+    /// gets mutated. For example in the "tsp" case it could take over a part of the tour of the
+    /// other individual (order crossover).
+    /// It is optional and the default implementation does nothing. The probability of a crossover
+    /// is set with `crossover_rate` in the `PopulationBuilder`.
+    fn crossover(&mut self, _other: &Self, _rng: &mut DarwinRng) {
+
+    }
+    /// This method returns the names of the mutation operators of the individual, for example
+    /// `&["swap", "rotate"]` for the tsp2 example. If there are operators, the population chooses
+    /// one of them for each individual and iteration (see `operator_selection` in the
+    /// `PopulationBuilder`) and calls `apply_operator` instead of `mutate`. How often each
+    /// operator has created a fitter offspring is recorded in the `operator_statistics` of the
+    /// population.
+    /// It is optional and the default implementation returns no operators.
+    fn operators(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// This method mutates the individual with the given operator, the index refers to the names
+    /// returned by `operators`.
+    /// It is optional and the default implementation just calls `mutate`.
+    fn apply_operator(&mut self, _operator: usize, rng: &mut DarwinRng) {
+        self.mutate(rng);
+    }
     /// This method calculates the fitness for the individual. Usually this is an expensive
     /// operation and a bit more difficult to implement, compared to the mutation method above.
     /// The lower the fitness value, the better (healthier) the individual is and the closer
     /// the individual is to the perfect solution. This can also correspont to the number of
     /// errors like for example in the sudoku or queens problem case.
-    fn calculate_fitness(&mut self) -> f64;
+    /// If higher values are better, set the direction to `Maximize` in the `SimulationBuilder`.
+    /// A NaN fitness is treated as the worst fitness. If this method (or `mutate` or `crossover`)
+    /// panics, the panic is caught and the individual is discarded. Both cases are counted in
+    /// `num_of_failures` of the population.
+    fn calculate_fitness(&mut self) -> Self::Fitness;
     /// This method resets each individual to an initial state.
     /// For example in the "queens" case it would reset the queens position randomly
-    /// (or all in the first row).
-    fn reset(&mut self);
+    /// (or all in the first row). If it panics, the panic is caught and the individual is
+    /// discarded.
+    fn reset(&mut self, rng: &mut DarwinRng);
+    /// This method returns the objectives of the individual for a multi-objective optimization
+    /// (see `multi_objective` in the `SimulationBuilder`). It is called right after
+    /// `calculate_fitness`, so the objectives should be calculated there and just be returned
+    /// here. All objectives follow the direction of the simulation (minimize or maximize).
+    /// It is optional and the default implementation returns no objectives.
+    fn objectives(&self) -> Vec<f64> {
+        Vec::new()
+    }
+    /// This method returns how much the individual violates the constraints of the problem,
+    /// for example the exceeded capacity or the sum of the missed time windows.
+    /// 0.0 means the individual is feasible. Like the objectives it is called right after
+    /// `calculate_fitness`. The `constraint_handling` of the `PopulationBuilder` decides how the
+    /// violation is taken into account.
+    /// It is optional and the default implementation returns 0.0 (no constraints).
+    fn constraint_violation(&self) -> f64 {
+        0.0
+    }
+    /// This method repairs an individual that violates the constraints, if that is possible.
+    /// It is called right before `calculate_fitness`, that is after each mutation and crossover.
+    /// It is optional and the default implementation does nothing.
+    fn repair(&mut self) {
+
+    }
+    /// This method returns the distance between two individuals in the search space, for
+    /// example the number of different positions of two tours. It is used to replace the most
+    /// similar individual when individuals migrate between populations.
+    /// It is optional and the default implementation returns `None`, then the difference of the
+    /// fitness values is used instead.
+    fn distance(&self, _other: &Self) -> Option<f64> {
+        None
+    }
     /// This method is called whenever a new fittest individual is found. It is usefull when you
     /// want to provide some additional information or do some statistics.
     /// It is optional and the default implementation does nothing.
@@ -96,44 +266,70 @@ pub trait Individual {
 
 #[cfg(test)]
 mod test {
+    use std::cmp::Ordering;
+
     use super::{IndividualWrapper, Individual};
+    use random::DarwinRng;
 
     struct IndividualTest1;
 
     impl Individual for IndividualTest1 {
-        fn mutate(&mut self) {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
         }
 
         fn calculate_fitness(&mut self) -> f64 {
             0.0
         }
 
-        fn reset(&mut self) {
+        fn reset(&mut self, _rng: &mut DarwinRng) {
 
         }
     }
 
     #[test]
     fn compare1() {
-        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 1.2, num_of_mutations: 21, id: 1};
-        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 5.93, num_of_mutations: 7, id: 1};
+        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 1.2, num_of_mutations: 21, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 5.93, num_of_mutations: 7, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
 
         assert!(individual2 > individual1);
     }
 
     #[test]
     fn compare2() {
-        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 3.78, num_of_mutations: 21, id: 1};
-        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 7.12, num_of_mutations: 7, id: 1};
+        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 3.78, num_of_mutations: 21, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 7.12, num_of_mutations: 7, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
 
         assert!(individual1 < individual2);
     }
 
     #[test]
     fn compare3() {
-        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 21.996, num_of_mutations: 11, id: 1};
-        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 21.996, num_of_mutations: 34, id: 1};
+        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 21.996, num_of_mutations: 11, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 21.996, num_of_mutations: 34, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
 
         assert!(individual1 == individual2);
     }
+
+    #[test]
+    fn compare_nan() {
+        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: f64::NAN, num_of_mutations: 11, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 1000.0, num_of_mutations: 34, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+
+        assert!(individual1 > individual2);
+        // Consistent with `cmp`: two NaN fitness values are equal.
+        let individual3 = IndividualWrapper{individual: IndividualTest1, fitness: f64::NAN, num_of_mutations: 5, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+        assert!(individual1 == individual3);
+    }
+
+    #[test]
+    fn compare_feasibility1() {
+        let individual1 = IndividualWrapper{individual: IndividualTest1, fitness: 1.0, num_of_mutations: 1, id: 1, objectives: Vec::new(), constraint_violation: 2.5, valid: true};
+        let individual2 = IndividualWrapper{individual: IndividualTest1, fitness: 9.0, num_of_mutations: 1, id: 1, objectives: Vec::new(), constraint_violation: 0.0, valid: true};
+
+        assert_eq!(individual2.compare_feasibility(&individual1), Ordering::Less);
+        assert!(!individual1.is_feasible());
+        assert!(individual2.is_feasible());
+    }
 }
@@ -0,0 +1,186 @@
+//! This module defines the islands of an asynchronous simulation: each population runs on its
+//! own thread and exchanges individuals and reports with the simulation through channels.
+
+use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Instant;
+
+use individual::{Individual, IndividualWrapper};
+use migration::{MigrationEdge, MigrationPolicy};
+use population::Population;
+use random::DarwinRng;
+use simulation::{self, Direction};
+use statistics::IterationStatistics;
+
+/// What an island reports to the simulation after each of its iterations.
+pub struct Report<T: Individual> {
+    /// The index of the population.
+    pub index: usize,
+    /// The fittest individual of the population, if it is better than the fittest individual
+    /// of all populations so far.
+    pub fittest: Option<IndividualWrapper<T>>,
+    /// The new reset limit, if the population has been reset in this iteration.
+    pub reset_limit: Option<u32>,
+    /// The number of failed evaluations of the population so far.
+    pub num_of_failures: u64,
+    /// The number of fitness evaluations of the population so far.
+    pub num_of_evaluations: u64,
+    /// The statistics of the population, if the history is recorded.
+    pub statistics: Option<IterationStatistics>,
+    /// The emigrants and the index of their target population.
+    pub emigrants: Vec<(usize, IndividualWrapper<T>)>,
+}
+
+/// One population of an asynchronous simulation, with everything it needs to run on its own
+/// thread.
+pub struct Island<T: Individual> {
+    /// The index of the population.
+    pub index: usize,
+    /// The population itself, it is returned when the island stops.
+    pub population: Population<T>,
+    /// The number of iterations of this population.
+    pub iteration: u32,
+    /// The immigrants from other populations.
+    pub immigrants: Receiver<IndividualWrapper<T>>,
+    /// The reports to the simulation.
+    pub reports: Sender<Report<T>>,
+    /// Set by the simulation when the stop condition is reached.
+    pub stop: Arc<AtomicBool>,
+    /// The fittest individual of all populations so far, shared by all islands.
+    pub fittest: Arc<Mutex<IndividualWrapper<T>>>,
+    /// The migration edges that start at this population.
+    pub edges: Vec<MigrationEdge>,
+    /// The migration policy of the simulation, if the population has none.
+    pub migration_policy: MigrationPolicy,
+    /// The random number generator for the migration.
+    pub rng: DarwinRng,
+    /// Is a lower or a higher fitness better ?
+    pub direction: Direction,
+    /// Calculate the statistics after each iteration ?
+    pub record_history: bool,
+    /// When the simulation has started to run.
+    pub start_time: Instant,
+    /// The total run time of the simulation before it has started to run.
+    pub start_time_in_ms: f64,
+}
+
+impl<T: Individual + Send + Sync + Clone> Island<T> {
+    /// Runs the population until the simulation sets the stop flag, and returns it.
+    pub fn run(mut self) -> Population<T> {
+        let mut best: Option<IndividualWrapper<T>> = None;
+
+        while !self.stop.load(atomic::Ordering::Relaxed) {
+            let num_of_resets = self.population.num_of_resets;
+            self.population.run_body();
+            self.iteration += 1;
+
+            self.insert_immigrants();
+            let emigrants = self.take_emigrants();
+
+            // Only report the fittest individual if it has improved, to avoid needless copies.
+            // The lock is held until the report has been sent, so that the new fittest
+            // individuals arrive in the right order.
+            let index = simulation::fittest_index(&self.population.population, self.direction);
+            let candidate = &self.population.population[index];
+            let mut global_fittest = None;
+            let mut fittest = None;
+
+            if candidate.valid && best.as_ref().map_or(true, |best|
+                candidate.compare_fitness(best, self.direction) == Ordering::Less) {
+                best = Some(candidate.clone());
+
+                let mut guard = self.fittest.lock().unwrap();
+                if candidate.compare_fitness(&guard, self.direction) == Ordering::Less {
+                    *guard = candidate.clone();
+                    fittest = best.clone();
+                    global_fittest = Some(guard);
+                }
+            }
+
+            // Call method `new_fittest_found` of the newly found fittest individual.
+            if fittest.is_some() {
+                self.population.population[index].individual.new_fittest_found();
+            }
+
+            let statistics = if self.record_history {
+                let time_in_ms = self.start_time_in_ms + simulation::duration_in_ms(self.start_time.elapsed());
+                Some(IterationStatistics::new(&self.population, self.iteration, time_in_ms))
+            } else {
+                None
+            };
+
+            let report = Report {
+                index: self.index,
+                fittest,
+                reset_limit: if self.population.num_of_resets > num_of_resets { Some(self.population.reset_limit) } else { None },
+                num_of_failures: self.population.num_of_failures,
+                num_of_evaluations: self.population.num_of_evaluations,
+                statistics,
+                emigrants,
+            };
+
+            // The simulation has stopped.
+            let sent = self.reports.send(report).is_ok();
+            drop(global_fittest);
+            if !sent {
+                break;
+            }
+        }
+
+        self.population
+    }
+
+    /// Inserts the immigrants that have arrived since the last iteration. They fill up the
+    /// population first, if emigrants have left it, then they replace individuals according to
+    /// the migration policy.
+    fn insert_immigrants(&mut self) {
+        let mut immigrants: Vec<IndividualWrapper<T>> = self.immigrants.try_iter().collect();
+        let num_of_missing = (self.population.num_of_individuals as usize).saturating_sub(self.population.population.len());
+
+        for immigrant in immigrants.drain(..num_of_missing.min(immigrants.len())) {
+            self.population.population.push(immigrant);
+            self.population.num_of_migrations += 1;
+        }
+
+        if immigrants.is_empty() {
+            return;
+        }
+
+        let policy = self.population.migration_policy.as_ref().unwrap_or(&self.migration_policy);
+        let replaced = policy.select_replaced(&self.population.population, &immigrants, &[], self.direction, &mut self.rng);
+
+        for (index, immigrant) in replaced.into_iter().zip(immigrants) {
+            self.population.population[index] = immigrant;
+            self.population.num_of_migrations += 1;
+        }
+    }
+
+    /// Chooses the emigrants along each edge whose interval is reached. If they are moved, they
+    /// are removed from the population (at least one individual is kept).
+    fn take_emigrants(&mut self) -> Vec<(usize, IndividualWrapper<T>)> {
+        let iteration = self.iteration;
+        let mut emigrants = Vec::new();
+        let mut holes = Vec::new();
+
+        for edge in self.edges.iter().filter(|edge| iteration % edge.interval == 0) {
+            let policy = self.population.migration_policy.as_ref().unwrap_or(&self.migration_policy);
+
+            for index in policy.select_emigrants(&self.population.population, self.direction, &mut self.rng) {
+                emigrants.push((edge.target, self.population.population[index].clone()));
+
+                if policy.move_migrants && !holes.contains(&index) && holes.len() + 1 < self.population.population.len() {
+                    holes.push(index);
+                }
+            }
+        }
+
+        holes.sort();
+        for index in holes.into_iter().rev() {
+            self.population.population.remove(index);
+        }
+
+        emigrants
+    }
+}
@@ -21,16 +21,42 @@
 
 #[macro_use] extern crate error_chain;
 #[macro_use] extern crate log;
+#[macro_use] extern crate serde_derive;
 extern crate jobsteal;
+extern crate rand;
+#[macro_use] extern crate serde;
+extern crate serde_json;
 
+pub mod fitness;
 pub mod individual;
 pub mod simulation;
 pub mod simulation_builder;
+pub mod simulation_handle;
 pub mod population;
 pub mod population_builder;
+pub mod selection;
+pub mod pareto;
+pub mod random;
+pub mod observer;
+pub mod statistics;
+pub mod migration;
+pub mod executor;
+pub mod operator;
+pub mod restart;
+mod island;
 
+pub use fitness::Fitness;
 pub use individual::Individual;
-pub use simulation::Simulation;
+pub use simulation::{Simulation, SimulationType, Direction, Scheduling, SimulationProgress};
 pub use simulation_builder::{SimulationBuilder};
-pub use population::Population;
+pub use simulation_handle::SimulationHandle;
+pub use population::{Population, TieBreak, SuperOptimization, ConstraintHandling, MutationAdaptation};
 pub use population_builder::{PopulationBuilder};
+pub use selection::Selection;
+pub use random::DarwinRng;
+pub use observer::Observer;
+pub use statistics::IterationStatistics;
+pub use migration::{MigrationTopology, MigrationEdge, MigrationPolicy, EmigrantSelection, Replacement};
+pub use executor::{Executor, Sequential, JobstealPool, SharedPool};
+pub use operator::{OperatorSelection, OperatorStatistics};
+pub use restart::{RestartTrigger, RestartPolicy};
@@ -0,0 +1,308 @@
+//! This module defines the topologies and policies for the migration of individuals between the
+//! populations (island model): which populations are connected, who leaves and who is replaced.
+
+use rand::Rng;
+
+use individual::{Individual, IndividualWrapper};
+use random::DarwinRng;
+use simulation::Direction;
+
+/// The `MigrationTopology` type. Specifies which populations exchange individuals when
+/// `share_fittest` is enabled. Except for `Global`, the populations are referred to by their
+/// index in the simulation (the order in which they have been added), not by their id.
+/// Along each edge of the topology individuals migrate from the source population to the
+/// target population every `share_every` iterations, see `MigrationPolicy`.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum MigrationTopology {
+    /// The fittest individual of the whole simulation is sent to every population, when a new
+    /// fittest individual has been found. Only the `replacement` of the `MigrationPolicy` is
+    /// used. This is the default.
+    Global,
+    /// Each population sends individuals to the next one, the last one to the first one.
+    Ring,
+    /// The first population exchanges individuals with all the other populations.
+    Star,
+    /// Each population sends individuals to all other populations.
+    FullyConnected,
+    /// Each population sends individuals to the given number of other populations. The
+    /// neighbours are chosen randomly once, when the simulation is created.
+    RandomNeighbours(usize),
+    /// The populations are the corners of a hypercube, each population exchanges individuals
+    /// with the populations whose index differs in exactly one bit.
+    Hypercube,
+    /// A user defined graph, each edge has its own migration interval.
+    Custom(Vec<MigrationEdge>),
+}
+
+/// The `MigrationEdge` type. Individuals migrate from the population `source` to the population
+/// `target` every `interval` iterations.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct MigrationEdge {
+    /// The index of the population that sends the individuals.
+    pub source: usize,
+    /// The index of the population that receives the individuals.
+    pub target: usize,
+    /// The number of iterations between two migrations, must be > 0.
+    pub interval: u32,
+}
+
+/// The `EmigrantSelection` type. Specifies which individuals leave a population.
+/// Invalid individuals never migrate.
+#[derive(Debug,Clone,Copy,PartialEq,Serialize,Deserialize)]
+pub enum EmigrantSelection {
+    /// The fittest individuals. This is the default.
+    Best,
+    /// Random individuals.
+    Random,
+    /// The winners of tournaments with the given size.
+    Tournament(usize),
+}
+
+/// The `Replacement` type. Specifies which individuals of a population are replaced by the
+/// immigrants.
+#[derive(Debug,Clone,Copy,PartialEq,Serialize,Deserialize)]
+pub enum Replacement {
+    /// The least fit individuals. This is the default.
+    Worst,
+    /// Random individuals.
+    Random,
+    /// For each immigrant the individual with the smallest distance to it, see the `distance`
+    /// method of the `Individual` trait.
+    MostSimilar,
+}
+
+/// The `MigrationPolicy` type. Specifies how many individuals migrate along an edge of the
+/// `MigrationTopology`, how they are chosen and whom they replace.
+/// Use `migration_policy` in the `SimulationBuilder` to set it for all populations or in the
+/// `PopulationBuilder` to set it for one population. The policy of the source population
+/// decides about the emigrants, the policy of the target population about the replacement.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct MigrationPolicy {
+    /// The number of individuals that migrate along each edge, must be > 0. Default: 1
+    pub num_of_migrants: usize,
+    /// How the emigrants are chosen. Default: `Best`
+    pub emigrant_selection: EmigrantSelection,
+    /// Whom the immigrants replace. Default: `Worst`
+    pub replacement: Replacement,
+    /// If true the emigrants leave their population and the immigrants take their places
+    /// first, otherwise copies of the emigrants are sent. Default: false
+    pub move_migrants: bool,
+}
+
+impl Default for MigrationPolicy {
+    fn default() -> MigrationPolicy {
+        MigrationPolicy {
+            num_of_migrants: 1,
+            emigrant_selection: EmigrantSelection::Best,
+            replacement: Replacement::Worst,
+            move_migrants: false,
+        }
+    }
+}
+
+impl MigrationPolicy {
+    /// Returns the indices of the emigrants of the given population.
+    pub fn select_emigrants<T: Individual>(&self, population: &[IndividualWrapper<T>], direction: Direction, rng: &mut DarwinRng) -> Vec<usize> {
+        let mut candidates: Vec<usize> = (0..population.len()).filter(|index| population[*index].valid).collect();
+        let num_of_migrants = self.num_of_migrants.min(candidates.len());
+
+        match self.emigrant_selection {
+            EmigrantSelection::Best => {
+                candidates.sort_by(|a, b| population[*a].compare_fitness(&population[*b], direction));
+                candidates.truncate(num_of_migrants);
+                candidates
+            }
+            EmigrantSelection::Random => {
+                rng.shuffle(&mut candidates);
+                candidates.truncate(num_of_migrants);
+                candidates
+            }
+            EmigrantSelection::Tournament(size) => {
+                let mut result = Vec::new();
+
+                while result.len() < num_of_migrants {
+                    let winner = (0..size.max(1)).map(|_| rng.gen_range(0, candidates.len()))
+                        .min_by(|a, b| population[candidates[*a]].compare_fitness(&population[candidates[*b]], direction)).unwrap();
+                    result.push(candidates.swap_remove(winner));
+                }
+
+                result
+            }
+        }
+    }
+
+    /// Returns the indices of the individuals of the given population that are replaced by the
+    /// immigrants, one for each immigrant as long as there are enough individuals.
+    /// The individuals in `excluded` are not replaced.
+    pub fn select_replaced<T: Individual>(&self, population: &[IndividualWrapper<T>], immigrants: &[IndividualWrapper<T>],
+        excluded: &[usize], direction: Direction, rng: &mut DarwinRng) -> Vec<usize> {
+        let mut candidates: Vec<usize> = (0..population.len()).filter(|index| !excluded.contains(index)).collect();
+        let num_of_replaced = immigrants.len().min(candidates.len());
+
+        match self.replacement {
+            Replacement::Worst => {
+                candidates.sort_by(|a, b| population[*b].compare_fitness(&population[*a], direction));
+                candidates.truncate(num_of_replaced);
+                candidates
+            }
+            Replacement::Random => {
+                rng.shuffle(&mut candidates);
+                candidates.truncate(num_of_replaced);
+                candidates
+            }
+            Replacement::MostSimilar => {
+                immigrants.iter().take(num_of_replaced).map(|immigrant| {
+                    let nearest = (0..candidates.len()).min_by(|a, b| {
+                        let distance_a = population[candidates[*a]].distance(immigrant);
+                        let distance_b = population[candidates[*b]].distance(immigrant);
+                        compare_distance(distance_a, distance_b)
+                    }).unwrap();
+                    candidates.swap_remove(nearest)
+                }).collect()
+            }
+        }
+    }
+}
+
+/// Compares two distances, NaN is the largest distance.
+fn compare_distance(distance1: f64, distance2: f64) -> ::std::cmp::Ordering {
+    Direction::Minimize.compare(&distance1, &distance2)
+}
+
+impl MigrationTopology {
+    /// Creates the edges of this topology for the given number of populations. `interval` is
+    /// used for all edges, except for `Custom` which has its own intervals.
+    /// `Global` has no edges.
+    pub fn edges(&self, num_of_populations: usize, interval: u32, rng: &mut DarwinRng) -> Vec<MigrationEdge> {
+        let edge = |source, target| MigrationEdge { source, target, interval };
+        let n = num_of_populations;
+
+        match *self {
+            MigrationTopology::Global => Vec::new(),
+            MigrationTopology::Ring => {
+                if n < 2 {
+                    Vec::new()
+                } else {
+                    (0..n).map(|source| edge(source, (source + 1) % n)).collect()
+                }
+            }
+            MigrationTopology::Star => {
+                (1..n).flat_map(|other| vec![edge(0, other), edge(other, 0)]).collect()
+            }
+            MigrationTopology::FullyConnected => {
+                (0..n).flat_map(|source| (0..n).filter(move |target| *target != source)
+                    .map(move |target| MigrationEdge { source, target, interval })).collect()
+            }
+            MigrationTopology::RandomNeighbours(num_of_neighbours) => {
+                let mut edges = Vec::new();
+
+                for source in 0..n {
+                    let mut others: Vec<usize> = (0..n).filter(|target| *target != source).collect();
+                    rng.shuffle(&mut others);
+                    others.truncate(num_of_neighbours);
+                    edges.extend(others.into_iter().map(|target| edge(source, target)));
+                }
+
+                edges
+            }
+            MigrationTopology::Hypercube => {
+                let mut edges = Vec::new();
+
+                for source in 0..n {
+                    let mut bit = 1;
+                    while bit < n {
+                        let target = source ^ bit;
+                        if target < n {
+                            edges.push(edge(source, target));
+                        }
+                        bit <<= 1;
+                    }
+                }
+
+                edges
+            }
+            MigrationTopology::Custom(ref edges) => edges.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use individual::{Individual, IndividualWrapper};
+    use random::DarwinRng;
+    use simulation::Direction;
+    use super::{MigrationTopology, MigrationEdge, MigrationPolicy, EmigrantSelection, Replacement};
+
+    struct IndividualTest1 {
+        position: f64,
+    }
+
+    impl Individual for IndividualTest1 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            0.0
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn distance(&self, other: &IndividualTest1) -> Option<f64> {
+            Some((self.position - other.position).abs())
+        }
+    }
+
+    fn make_wrapper(fitness: f64, position: f64, valid: bool) -> IndividualWrapper<IndividualTest1> {
+        IndividualWrapper{individual: IndividualTest1 { position }, fitness, num_of_mutations: 1, id: 1,
+            objectives: Vec::new(), constraint_violation: 0.0, valid}
+    }
+
+    fn pairs(topology: MigrationTopology, num_of_populations: usize) -> Vec<(usize, usize)> {
+        topology.edges(num_of_populations, 5, &mut DarwinRng::from_seed(1)).iter()
+            .map(|edge| (edge.source, edge.target)).collect()
+    }
+
+    #[test]
+    fn edges1() {
+        assert!(pairs(MigrationTopology::Global, 4).is_empty());
+        assert_eq!(pairs(MigrationTopology::Ring, 3), vec![(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(pairs(MigrationTopology::Star, 3), vec![(0, 1), (1, 0), (0, 2), (2, 0)]);
+        assert_eq!(pairs(MigrationTopology::FullyConnected, 3).len(), 6);
+        assert_eq!(pairs(MigrationTopology::Hypercube, 4), vec![(0, 1), (0, 2), (1, 0), (1, 3), (2, 3), (2, 0), (3, 2), (3, 1)]);
+
+        let random = pairs(MigrationTopology::RandomNeighbours(2), 5);
+        assert_eq!(random.len(), 10);
+        assert!(random.iter().all(|&(source, target)| source != target));
+
+        let custom = vec![MigrationEdge { source: 1, target: 0, interval: 3 }];
+        assert_eq!(MigrationTopology::Custom(custom.clone()).edges(2, 5, &mut DarwinRng::from_seed(1)), custom);
+    }
+
+    #[test]
+    fn policy1() {
+        let mut rng = DarwinRng::from_seed(1);
+        let population = vec![make_wrapper(3.0, 0.0, true), make_wrapper(1.0, 10.0, true),
+            make_wrapper(0.0, 20.0, false), make_wrapper(2.0, 30.0, true)];
+
+        let policy = MigrationPolicy { num_of_migrants: 2, .. MigrationPolicy::default() };
+        assert_eq!(policy.select_emigrants(&population, Direction::Minimize, &mut rng), vec![1, 3]);
+        assert_eq!(policy.select_emigrants(&population, Direction::Maximize, &mut rng), vec![0, 3]);
+
+        // Invalid individuals never migrate.
+        let policy = MigrationPolicy { num_of_migrants: 4, emigrant_selection: EmigrantSelection::Random, .. MigrationPolicy::default() };
+        let mut emigrants = policy.select_emigrants(&population, Direction::Minimize, &mut rng);
+        emigrants.sort();
+        assert_eq!(emigrants, vec![0, 1, 3]);
+
+        // The invalid individual is the least fit one.
+        let immigrants = vec![make_wrapper(0.5, 29.0, true), make_wrapper(0.5, 1.0, true)];
+        assert_eq!(policy.select_replaced(&population, &immigrants, &[], Direction::Minimize, &mut rng), vec![2, 0]);
+        assert_eq!(policy.select_replaced(&population, &immigrants, &[2], Direction::Minimize, &mut rng), vec![0, 3]);
+
+        let policy = MigrationPolicy { replacement: Replacement::MostSimilar, .. MigrationPolicy::default() };
+        assert_eq!(policy.select_replaced(&population, &immigrants, &[], Direction::Minimize, &mut rng), vec![3, 0]);
+    }
+}
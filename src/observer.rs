@@ -0,0 +1,34 @@
+//! This module defines the `Observer` trait for observers that get notified about the events
+//! of a simulation: iterations, new fittest individuals, population resets and migrations.
+
+use individual::{Individual, IndividualWrapper};
+use simulation::SimulationResult;
+
+/// This trait has to be implemented for an observer of the simulation. Use the `observer`
+/// method of the `SimulationBuilder` to register it, several observers can be registered.
+/// All methods are called from the thread that runs the simulation, after the populations
+/// have finished their iteration. All methods are optional and the default implementations
+/// do nothing.
+pub trait Observer<T: Individual + Send + Sync>: Send {
+    /// Called after each iteration, when the results have been updated.
+    fn on_iteration(&mut self, _result: &SimulationResult<T>, _iteration: u32) {
+
+    }
+    /// Called when a new fittest individual of the whole simulation has been found in the
+    /// population with the given id.
+    fn on_new_fittest(&mut self, _population_id: u32, _fittest: &IndividualWrapper<T>) {
+
+    }
+    /// Called when all individuals of a population have been reset because the reset limit
+    /// was reached. `new_reset_limit` is the reset limit for the next reset.
+    fn on_population_reset(&mut self, _population_id: u32, _new_reset_limit: u32) {
+
+    }
+    /// Called when an individual of the population `source_id` is copied into the population
+    /// `target_id`, for example when the fittest individual is shared (see `share_fittest`).
+    /// `source_id` is the population the migrant has been taken from, the `id` of the migrant
+    /// is the population it has been created in.
+    fn on_migration(&mut self, _source_id: u32, _target_id: u32, _migrant: &IndividualWrapper<T>) {
+
+    }
+}
@@ -0,0 +1,220 @@
+//! This module defines the strategies that choose between the mutation operators of the
+//! individuals (adaptive operator selection) and the statistics they are based on.
+
+use std::cmp::Ordering;
+
+use rand::Rng;
+
+use random::DarwinRng;
+
+/// The `OperatorSelection` type. Specifies how the population chooses the mutation operator of
+/// each individual (see `operators` in the `Individual` trait). An operator is rewarded if the
+/// individual is fitter than its parent after the mutation. The choice is made for all
+/// individuals at the beginning of each iteration, the statistics are updated at the end.
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+pub enum OperatorSelection {
+    /// Each operator is chosen with the same probability. This is the default.
+    #[default]
+    Uniform,
+    /// Probability matching: the probability of an operator is proportional to its quality,
+    /// but at least `min_probability`. The quality is the success rate, where the recent
+    /// iterations count more, depending on `adaptation_rate`.
+    ProbabilityMatching {
+        /// The minimum probability of each operator (0.0 <= min_probability < 1 / number of operators).
+        min_probability: f64,
+        /// How fast the quality follows the recent success rate (0.0 < adaptation_rate <= 1.0).
+        adaptation_rate: f64,
+    },
+    /// Adaptive pursuit: the probability of the operator with the best quality approaches the
+    /// maximum probability, the other ones approach `min_probability`. This favours the best
+    /// operator much more than `ProbabilityMatching`.
+    AdaptivePursuit {
+        /// The minimum probability of each operator (0.0 <= min_probability < 1 / number of operators).
+        min_probability: f64,
+        /// How fast the quality follows the recent success rate (0.0 < adaptation_rate <= 1.0).
+        adaptation_rate: f64,
+        /// How fast the probabilities approach their target (0.0 < learning_rate <= 1.0).
+        learning_rate: f64,
+    },
+    /// Multi-armed bandit (UCB1): the operator with the highest upper confidence bound of its
+    /// success rate is chosen. Each operator that is chosen for an individual counts as applied
+    /// for the choice of the next individual, so the operators are spread over the population.
+    Bandit {
+        /// The weight of the confidence bound (>= 0.0), higher values explore more.
+        exploration: f64,
+    },
+}
+
+/// The `OperatorStatistics` type. How often one mutation operator of a population has been
+/// applied and how successful it was.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct OperatorStatistics {
+    /// The name of the operator, see `operators` in the `Individual` trait.
+    pub name: String,
+    /// How many individuals have been mutated with this operator.
+    pub num_of_applications: u64,
+    /// How many of these individuals were fitter than their parent.
+    pub num_of_successes: u64,
+    /// The estimated success rate of the operator.
+    pub quality: f64,
+    /// The probability that the operator is chosen in the next iteration
+    /// (not used by the `Bandit`).
+    pub probability: f64,
+}
+
+impl OperatorStatistics {
+    /// Creates the (empty) statistics for the given operators.
+    pub fn new(names: &[&str]) -> Vec<OperatorStatistics> {
+        names.iter().map(|name| OperatorStatistics {
+            name: name.to_string(),
+            num_of_applications: 0,
+            num_of_successes: 0,
+            quality: 0.0,
+            probability: 1.0 / names.len() as f64,
+        }).collect()
+    }
+}
+
+impl OperatorSelection {
+    /// Returns true if the parameters are valid for the given number of operators.
+    pub fn is_valid(&self, num_of_operators: usize) -> bool {
+        let is_rate = |rate: f64| rate > 0.0 && rate <= 1.0;
+        let is_min_probability = |probability: f64| probability >= 0.0 && probability * (num_of_operators as f64) < 1.0;
+
+        match *self {
+            OperatorSelection::Uniform => true,
+            OperatorSelection::ProbabilityMatching{ min_probability, adaptation_rate } =>
+                is_min_probability(min_probability) && is_rate(adaptation_rate),
+            OperatorSelection::AdaptivePursuit{ min_probability, adaptation_rate, learning_rate } =>
+                is_min_probability(min_probability) && is_rate(adaptation_rate) && is_rate(learning_rate),
+            OperatorSelection::Bandit{ exploration } => exploration >= 0.0,
+        }
+    }
+
+    /// Chooses the operator for each of the `num_of_individuals` individuals.
+    pub fn choose(&self, statistics: &[OperatorStatistics], num_of_individuals: usize, rng: &mut DarwinRng) -> Vec<usize> {
+        match *self {
+            OperatorSelection::Bandit{ exploration } => {
+                let mut counts: Vec<f64> = statistics.iter().map(|operator| operator.num_of_applications as f64).collect();
+
+                (0..num_of_individuals).map(|_| {
+                    let total: f64 = counts.iter().sum();
+                    let bound = |index: usize| if counts[index] == 0.0 {
+                        f64::INFINITY
+                    } else {
+                        statistics[index].quality + exploration * (2.0 * total.ln() / counts[index]).sqrt()
+                    };
+
+                    // The first operator with the highest bound wins.
+                    let chosen = (0..counts.len()).fold(0, |best, index|
+                        if bound(index).partial_cmp(&bound(best)) == Some(Ordering::Greater) { index } else { best });
+                    counts[chosen] += 1.0;
+                    chosen
+                }).collect()
+            }
+            _ => (0..num_of_individuals).map(|_| {
+                let mut value = rng.gen::<f64>() * statistics.iter().map(|operator| operator.probability).sum::<f64>();
+
+                statistics.iter().position(|operator| {
+                    value -= operator.probability;
+                    value < 0.0
+                }).unwrap_or(statistics.len() - 1)
+            }).collect(),
+        }
+    }
+
+    /// Updates the statistics after an iteration, `results` contains the number of applications
+    /// and successes of each operator in this iteration.
+    pub fn update(&self, statistics: &mut [OperatorStatistics], results: &[(u64, u64)]) {
+        for (operator, &(num_of_applications, num_of_successes)) in statistics.iter_mut().zip(results) {
+            operator.num_of_applications += num_of_applications;
+            operator.num_of_successes += num_of_successes;
+
+            if num_of_applications == 0 {
+                continue;
+            }
+
+            operator.quality = match *self {
+                OperatorSelection::ProbabilityMatching{ adaptation_rate, .. } |
+                OperatorSelection::AdaptivePursuit{ adaptation_rate, .. } => {
+                    let reward = num_of_successes as f64 / num_of_applications as f64;
+                    operator.quality + adaptation_rate * (reward - operator.quality)
+                }
+                _ => operator.num_of_successes as f64 / operator.num_of_applications as f64,
+            };
+        }
+
+        let num_of_operators = statistics.len() as f64;
+
+        match *self {
+            OperatorSelection::ProbabilityMatching{ min_probability, .. } => {
+                let total: f64 = statistics.iter().map(|operator| operator.quality).sum();
+
+                for operator in statistics.iter_mut() {
+                    operator.probability = if total > 0.0 {
+                        min_probability + (1.0 - num_of_operators * min_probability) * operator.quality / total
+                    } else {
+                        1.0 / num_of_operators
+                    };
+                }
+            }
+            OperatorSelection::AdaptivePursuit{ min_probability, learning_rate, .. } => {
+                let max_probability = 1.0 - (num_of_operators - 1.0) * min_probability;
+                let best = (0..statistics.len()).fold(0, |best, index|
+                    if statistics[index].quality > statistics[best].quality { index } else { best });
+
+                for (index, operator) in statistics.iter_mut().enumerate() {
+                    let target = if index == best { max_probability } else { min_probability };
+                    operator.probability += learning_rate * (target - operator.probability);
+                }
+            }
+            OperatorSelection::Uniform | OperatorSelection::Bandit{ .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use random::DarwinRng;
+
+    use super::{OperatorSelection, OperatorStatistics};
+
+    #[test]
+    fn update1() {
+        let selection = OperatorSelection::ProbabilityMatching{ min_probability: 0.1, adaptation_rate: 1.0 };
+        let mut statistics = OperatorStatistics::new(&["a", "b", "c"]);
+        selection.update(&mut statistics, &[(10, 6), (10, 2), (0, 0)]);
+
+        assert_eq!(statistics[0].num_of_applications, 10);
+        assert_eq!(statistics[0].num_of_successes, 6);
+        assert_eq!(statistics[0].quality, 0.6);
+        assert!((statistics[0].probability - 0.625).abs() < 1e-9);
+        assert!((statistics[1].probability - 0.275).abs() < 1e-9);
+        assert!((statistics[2].probability - 0.1).abs() < 1e-9);
+
+        let selection = OperatorSelection::AdaptivePursuit{ min_probability: 0.1, adaptation_rate: 1.0, learning_rate: 1.0 };
+        let mut statistics = OperatorStatistics::new(&["a", "b", "c"]);
+        selection.update(&mut statistics, &[(10, 2), (10, 6), (0, 0)]);
+
+        assert!((statistics[0].probability - 0.1).abs() < 1e-9);
+        assert!((statistics[1].probability - 0.8).abs() < 1e-9);
+        assert!(!OperatorSelection::AdaptivePursuit{ min_probability: 0.5, adaptation_rate: 1.0, learning_rate: 1.0 }.is_valid(2));
+    }
+
+    #[test]
+    fn choose1() {
+        let mut rng = DarwinRng::from_seed(1);
+        let mut statistics = OperatorStatistics::new(&["a", "b", "c"]);
+
+        // Untried operators are chosen first.
+        let selection = OperatorSelection::Bandit{ exploration: 1.0 };
+        assert_eq!(selection.choose(&statistics, 3, &mut rng), vec![0, 1, 2]);
+
+        selection.update(&mut statistics, &[(10, 0), (10, 9), (10, 0)]);
+        assert_eq!(selection.choose(&statistics, 1, &mut rng), vec![1]);
+
+        statistics[0].probability = 0.0;
+        statistics[2].probability = 0.0;
+        assert!(OperatorSelection::Uniform.choose(&statistics, 10, &mut rng).iter().all(|operator| *operator == 1));
+    }
+}
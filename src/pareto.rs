@@ -0,0 +1,148 @@
+//! This module defines the helper functions for multi-objective optimization:
+//! Pareto dominance, non-dominated sorting and crowding distance (NSGA-II).
+
+use std::cmp::Ordering;
+use std::f64;
+
+use simulation::Direction;
+
+/// Returns true if the objectives `a` dominate the objectives `b`: `a` is not worse than `b` in
+/// all objectives and better in at least one objective.
+pub fn dominates(a: &[f64], b: &[f64], direction: Direction) -> bool {
+    let mut better = false;
+
+    for (value_a, value_b) in a.iter().zip(b.iter()) {
+        if direction.is_better(value_b, value_a) {
+            return false;
+        }
+        if direction.is_better(value_a, value_b) {
+            better = true;
+        }
+    }
+
+    better
+}
+
+/// Non-dominated sorting: calculates the front (rank) for each objective vector.
+/// Rank 0 is the Pareto front: these are not dominated by any other objective vector.
+/// Rank 1 is only dominated by rank 0, and so on.
+pub fn non_dominated_sort(objectives: &[&[f64]], direction: Direction) -> Vec<u32> {
+    let num_of_items = objectives.len();
+    // For each item: which items does it dominate ?
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); num_of_items];
+    // For each item: by how many items is it dominated ?
+    let mut dominated_by = vec![0; num_of_items];
+    let mut ranks = vec![0; num_of_items];
+
+    for i in 0..num_of_items {
+        for j in (i + 1)..num_of_items {
+            if dominates(objectives[i], objectives[j], direction) {
+                dominated[i].push(j);
+                dominated_by[j] += 1;
+            } else if dominates(objectives[j], objectives[i], direction) {
+                dominated[j].push(i);
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut front: Vec<usize> = (0..num_of_items).filter(|i| dominated_by[*i] == 0).collect();
+    let mut rank = 0;
+
+    while !front.is_empty() {
+        let mut next_front = Vec::new();
+
+        for &i in &front {
+            ranks[i] = rank;
+            for &j in &dominated[i] {
+                dominated_by[j] -= 1;
+                if dominated_by[j] == 0 {
+                    next_front.push(j);
+                }
+            }
+        }
+
+        front = next_front;
+        rank += 1;
+    }
+
+    ranks
+}
+
+/// Calculates the crowding distance for each objective vector inside its front (rank).
+/// The higher the distance, the less crowded is the region around this item.
+/// The items at the boundary of a front get an infinite distance.
+pub fn crowding_distance(objectives: &[&[f64]], ranks: &[u32]) -> Vec<f64> {
+    let num_of_items = objectives.len();
+    let num_of_objectives = objectives.iter().map(|values| values.len()).min().unwrap_or(0);
+    let mut distance = vec![0.0; num_of_items];
+    let max_rank = ranks.iter().cloned().max().unwrap_or(0);
+    // All the values of one objective.
+    let columns: Vec<Vec<f64>> = (0..num_of_objectives)
+        .map(|objective| objectives.iter().map(|values| values[objective]).collect()).collect();
+
+    for rank in 0..(max_rank + 1) {
+        let mut front: Vec<usize> = (0..num_of_items).filter(|i| ranks[*i] == rank).collect();
+
+        for column in &columns {
+            let value = |index: usize| column[index];
+
+            front.sort_by(|a, b| value(*a).partial_cmp(&value(*b)).unwrap_or(Ordering::Equal));
+
+            let first = front[0];
+            let last = front[front.len() - 1];
+            let range = value(last) - value(first);
+
+            distance[first] = f64::INFINITY;
+            distance[last] = f64::INFINITY;
+
+            if range > 0.0 {
+                for window in front.windows(3) {
+                    distance[window[1]] += (value(window[2]) - value(window[0])) / range;
+                }
+            }
+        }
+    }
+
+    distance
+}
+
+/// Compares two items by rank (lower is better) and crowding distance (higher is better).
+/// The better one comes first (`Ordering::Less`).
+pub fn compare(rank1: u32, distance1: f64, rank2: u32, distance2: f64) -> Ordering {
+    rank1.cmp(&rank2).then_with(||
+        distance2.partial_cmp(&distance1).unwrap_or(Ordering::Equal))
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64;
+
+    use super::{dominates, non_dominated_sort, crowding_distance};
+    use simulation::Direction;
+
+    #[test]
+    fn dominates1() {
+        assert!(dominates(&[1.0, 2.0], &[2.0, 2.0], Direction::Minimize));
+        assert!(!dominates(&[1.0, 2.0], &[1.0, 2.0], Direction::Minimize));
+        assert!(!dominates(&[1.0, 3.0], &[2.0, 2.0], Direction::Minimize));
+        assert!(dominates(&[2.0, 2.0], &[1.0, 2.0], Direction::Maximize));
+    }
+
+    #[test]
+    fn non_dominated_sort1() {
+        let objectives: Vec<&[f64]> = vec![&[1.0, 4.0], &[2.0, 2.0], &[3.0, 3.0], &[4.0, 1.0], &[5.0, 5.0]];
+
+        assert_eq!(non_dominated_sort(&objectives, Direction::Minimize), vec![0, 0, 1, 0, 2]);
+    }
+
+    #[test]
+    fn crowding_distance1() {
+        let objectives: Vec<&[f64]> = vec![&[1.0, 4.0], &[2.0, 2.0], &[4.0, 1.0]];
+        let distance = crowding_distance(&objectives, &[0, 0, 0]);
+
+        assert_eq!(distance[0], f64::INFINITY);
+        assert_eq!(distance[2], f64::INFINITY);
+        assert_eq!(distance[1], 2.0);
+    }
+}
@@ -13,11 +13,178 @@
 //!
 //!
 
+use std::cmp::Ordering;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use rand::Rng;
+
+use executor::Job;
+use fitness::Fitness;
 use individual::{Individual, IndividualWrapper};
+use migration::MigrationPolicy;
+use operator::{OperatorSelection, OperatorStatistics};
+use selection::{Selection, Truncation, Tournament};
+use pareto;
+use random::DarwinRng;
+use restart::{RestartTrigger, RestartPolicy, RestartState};
+use simulation::{self, Direction};
+
+/// The `TieBreak` type. Specifies the order of individuals with equal fitness when the population
+/// is sorted. This allows a neutral drift across fitness plateaus, which is common for problems
+/// like sudoku or queens, where the fitness is the number of errors.
+/// See https://github.com/willi-kappler/darwin-rs/issues/7
+pub enum TieBreak<T: Individual> {
+    /// Individuals with equal fitness are shuffled randomly.
+    Random,
+    /// The mutated individuals (offspring) come first. This is the default.
+    PreferOffspring,
+    /// The original individuals (parents) come first.
+    PreferParent,
+    /// A user defined function compares individuals with equal fitness (secondary key).
+    Custom(fn(&T, &T) -> Ordering),
+}
+
+/// Writes a tie break to a checkpoint (use with `#[serde(with = "...")]`). A `Custom` tie break
+/// is written as `null` and restored as a `Custom` tie break that has still to be set again,
+/// see `restore_tie_break` in the `SimulationBuilder`.
+mod checkpoint_tie_break {
+    use std::cmp::Ordering;
+
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    use individual::Individual;
+    use super::TieBreak;
+
+    /// The tie breaks that can be saved.
+    #[derive(Serialize,Deserialize)]
+    enum BuiltInTieBreak {
+        Random,
+        PreferOffspring,
+        PreferParent,
+    }
+
+    fn unrestored<T>(_a: &T, _b: &T) -> Ordering {
+        panic!("the custom tie break has not been restored from the checkpoint");
+    }
+
+    pub fn serialize<T: Individual, S: Serializer>(tie_break: &TieBreak<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *tie_break {
+            TieBreak::Random => Some(BuiltInTieBreak::Random),
+            TieBreak::PreferOffspring => Some(BuiltInTieBreak::PreferOffspring),
+            TieBreak::PreferParent => Some(BuiltInTieBreak::PreferParent),
+            TieBreak::Custom(_) => None,
+        }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Individual, D: Deserializer<'de>>(deserializer: D) -> Result<TieBreak<T>, D::Error> {
+        Ok(match Option::<BuiltInTieBreak>::deserialize(deserializer)? {
+            Some(BuiltInTieBreak::Random) => TieBreak::Random,
+            Some(BuiltInTieBreak::PreferOffspring) => TieBreak::PreferOffspring,
+            Some(BuiltInTieBreak::PreferParent) => TieBreak::PreferParent,
+            None => TieBreak::Custom(unrestored::<T>),
+        })
+    }
+}
+
+impl<T: Individual> Clone for TieBreak<T> {
+    fn clone(&self) -> TieBreak<T> {
+        match *self {
+            TieBreak::Random => TieBreak::Random,
+            TieBreak::PreferOffspring => TieBreak::PreferOffspring,
+            TieBreak::PreferParent => TieBreak::PreferParent,
+            TieBreak::Custom(compare) => TieBreak::Custom(compare),
+        }
+    }
+}
+
+/// The `SuperOptimization` type. Specifies what happens with the intermediate states of an
+/// individual that mutates several times in one iteration (`num_of_mutations` > 1).
+/// Evaluating the intermediate states costs additional fitness calculations.
+/// See https://github.com/willi-kappler/darwin-rs/issues/10
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum SuperOptimization {
+    /// Only the final state is evaluated, the intermediate states are lost. This is the default.
+    Off,
+    /// The fitness is calculated after each mutation step and the fittest state is kept.
+    KeepBest,
+    /// The fitness is calculated after each mutation step and all intermediate states are
+    /// added to the candidates for the selection.
+    KeepAll,
+}
+
+/// The `ConstraintHandling` type. Specifies how the constraint violation of the individuals
+/// (see `constraint_violation` in the `Individual` trait) is taken into account when the
+/// population is sorted. In a multi-objective optimization the feasibility rules are always used.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum ConstraintHandling {
+    /// Deb's feasibility rules: a feasible individual is always better than an infeasible one,
+    /// two feasible individuals are compared by fitness and two infeasible individuals by their
+    /// constraint violation. This is the default.
+    FeasibilityFirst,
+    /// The constraint violation multiplied by `weight` is added to the fitness (subtracted when
+    /// maximizing) and the individuals are sorted by this penalized fitness.
+    Penalty {
+        /// The weight of the constraint violation.
+        weight: f64,
+    },
+    /// Like `Penalty`, but the weight adapts after each iteration: if the fittest individual
+    /// is infeasible, the weight is multiplied by `factor`, otherwise it is divided by `factor`.
+    AdaptivePenalty {
+        /// The current weight of the constraint violation.
+        weight: f64,
+        /// The factor (> 1.0) used to increase or decrease the weight.
+        factor: f64,
+    },
+}
+
+/// The `MutationAdaptation` type. Specifies how the number of mutations of the individuals
+/// (see `mutation_rate` in the `PopulationBuilder`) changes while the simulation is running.
+/// The `OneFifthRule` and `Decay` scale the number of mutations of all individuals with the
+/// `mutation_scale` of the population, which is set back to 1.0 when the population is reset.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum MutationAdaptation {
+    /// The number of mutations of each position in the sorted population never changes.
+    /// This is the default.
+    Fixed,
+    /// Rechenberg's 1/5th success rule: after each `interval` iterations the number of mutations
+    /// is multiplied by `factor` if more than 1/5 of the offspring were fitter than their parent,
+    /// and divided by `factor` if less were.
+    OneFifthRule {
+        /// The factor (> 1.0) used to increase or decrease the number of mutations.
+        factor: f64,
+        /// The number of iterations (> 0) between two adaptations.
+        interval: u32,
+        /// The upper limit (> 0) for the number of mutations.
+        max_mutations: u32,
+    },
+    /// Self-adaptation: before an individual mutates, its own number of mutations is multiplied
+    /// or divided by `factor` or stays the same, with equal probability. The offspring inherits
+    /// this number and it is not restored after the selection, so the numbers of mutations that
+    /// create fit offspring spread through the population.
+    SelfAdaptive {
+        /// The factor (> 1.0) used to increase or decrease the number of mutations.
+        factor: f64,
+        /// The upper limit (> 0) for the number of mutations.
+        max_mutations: u32,
+    },
+    /// The number of mutations decays over time: it is multiplied by `factor` after each
+    /// iteration, but each individual mutates at least once. So the population explores at the
+    /// beginning and fine tunes at the end.
+    Decay {
+        /// The factor (0.0 < factor <= 1.0) applied after each iteration.
+        factor: f64,
+    },
+}
 
 /// The `Population` type. Contains the actual individuals (through a wrapper) and informations
 /// like the `reset_limit`. Use the `PopulationBuilder` in your main program to create populations.
-#[derive(Clone)]
+/// The built-in strategies (`selection`, `parent_selection` and `tie_break`) are saved in a
+/// checkpoint, user defined ones have to be set again with the `restore_*` methods of the
+/// `SimulationBuilder`.
+#[derive(Clone,Serialize,Deserialize)]
+#[serde(bound(serialize = "T: ::serde::Serialize, T::Fitness: ::serde::Serialize",
+    deserialize = "T: ::serde::de::DeserializeOwned, T::Fitness: ::serde::de::DeserializeOwned"))]
 pub struct Population<T: Individual> {
     /// The number of individuals for this population.
     pub num_of_individuals: u32,
@@ -43,52 +210,385 @@ pub struct Population<T: Individual> {
     /// have the most fittest individuals ? This may help you to set the correct parameters for
     /// your simulations.
     pub id: u32,
+    /// The probability (0.0 - 1.0) that an individual is recombined with another individual
+    /// of the same population before it gets mutated. Default: 0.0 (no crossover).
+    pub crossover_rate: f64,
+    /// The strategy to select the individuals that survive an iteration.
+    /// Default: `Truncation`, keep only the fittest individuals.
+    #[serde(with = "::selection::checkpoint_selection")]
+    pub selection: Arc<dyn Selection>,
+    /// The strategy to select the partner of an individual for a crossover.
+    /// Default: `Tournament` with size 2.
+    #[serde(with = "::selection::checkpoint_selection")]
+    pub parent_selection: Arc<dyn Selection>,
+    /// Is a lower or a higher fitness better ? This is set by the `SimulationBuilder`.
+    pub direction: Direction,
+    /// Sort the individuals by Pareto rank and crowding distance of their objectives instead
+    /// of their fitness. This is set by the `SimulationBuilder`.
+    pub multi_objective: bool,
+    /// How to sort individuals with equal fitness. Default: `PreferOffspring`.
+    #[serde(with = "checkpoint_tie_break")]
+    pub tie_break: TieBreak<T>,
+    /// Keep the intermediate states of the mutation steps ? Default: `Off`.
+    pub super_optimization: SuperOptimization,
+    /// How to rank individuals that violate constraints. Default: `FeasibilityFirst`.
+    pub constraint_handling: ConstraintHandling,
+    /// The random number generator of this population, it is passed to the individuals.
+    /// Set by the `SimulationBuilder` if a seed is given, otherwise it has a random seed.
+    pub rng: DarwinRng,
     /// Count how often this population has created (found) the fittest individual. This may help
     /// you to fine tune the parameters for the population and the simulation in general.
-    pub fitness_counter: u64
+    pub fitness_counter: u64,
+    /// Count how many evaluations of this population have failed: the individual panicked in
+    /// `mutate`, `crossover` or `calculate_fitness`, or the fitness was NaN.
+    pub num_of_failures: u64,
+    /// Count how many fitness evaluations this population has done, including the failed ones.
+    pub num_of_evaluations: u64,
+    /// Count how often all individuals of this population have been reset.
+    pub num_of_resets: u64,
+    /// Count how many individuals have migrated into this population from other populations.
+    pub num_of_migrations: u64,
+    /// The migration policy of this population, if it differs from the one of the simulation.
+    pub migration_policy: Option<MigrationPolicy>,
+    /// How the number of mutations changes during the simulation. Default: `Fixed`.
+    pub mutation_adaptation: MutationAdaptation,
+    /// The current factor for the number of mutations of all individuals, it is changed by the
+    /// `OneFifthRule` and `Decay` adaptation.
+    pub mutation_scale: f64,
+    /// The number of offspring that were fitter than their parent since the last adaptation
+    /// of the `OneFifthRule`.
+    pub num_of_successes: u64,
+    /// The number of offspring since the last adaptation of the `OneFifthRule`.
+    pub num_of_trials: u64,
+    /// The number of iterations since the last adaptation of the `OneFifthRule`.
+    pub adaptation_counter: u32,
+    /// How to choose between the mutation operators of the individuals, if they have any
+    /// (see `operators` in the `Individual` trait). Default: `Uniform`.
+    pub operator_selection: OperatorSelection,
+    /// How often each mutation operator has been applied and how successful it was.
+    /// Empty if the individuals have no operators.
+    pub operator_statistics: Vec<OperatorStatistics>,
+    /// When all individuals are reset. Default: `ResetLimit`.
+    pub restart_trigger: RestartTrigger,
+    /// The size of the population after a restart. Default: `Reset`.
+    pub restart_policy: RestartPolicy,
+    /// The book keeping of the restarts.
+    pub restart_state: RestartState,
+}
+
+/// The default strategy for survivor selection: `Truncation`.
+pub fn default_selection() -> Arc<dyn Selection> {
+    Arc::new(Truncation)
+}
+
+/// The default strategy for parent selection: `Tournament` with size 2.
+pub fn default_parent_selection() -> Arc<dyn Selection> {
+    Arc::new(Tournament{ size: 2 })
+}
+
+/// The default tie break: `PreferOffspring`.
+pub fn default_tie_break<T: Individual>() -> TieBreak<T> {
+    TieBreak::PreferOffspring
 }
 
 impl<T: Individual + Send + Sync + Clone> Population<T> {
     /// Just calculates the fitness for each individual.
     /// Usually this is the most computational expensive operation, so optimize the
     /// `calculate_fitness` method of your data structure ;-)
+    /// Individuals that panic or return NaN are counted in `num_of_failures`.
     pub fn calculate_fitness(&mut self) {
         for wrapper in &mut self.population {
-            wrapper.fitness = wrapper.individual.calculate_fitness();
+            wrapper.calculate_fitness();
         }
+
+        self.num_of_evaluations += self.population.len() as u64;
+        self.num_of_failures += self.population.iter().filter(|wrapper| wrapper.has_failed()).count() as u64;
     }
 
     /// This is the body that gets called for every iteration.
     /// This function does the following:
     ///
-    /// 1. Check if the reset limit is reached. If it is, this whole population is
-    /// discarded and re-initialized from the start. All the information about the
-    /// current fittest individual is lost. This is done to avoid local minima.
+    /// 1. Check if the population has to be restarted (see `restart_trigger`). If it does,
+    ///    this whole population is discarded and re-initialized from the start, its new size
+    ///    is given by the `restart_policy`. All the information about the current fittest
+    ///    individual is lost. This is done to avoid local minima.
     ///
     /// 2. Clone the current population.
     ///
-    /// 3. Mutate the current population using the `mutate_population` function.
+    /// 3. Recombine each individual with a partner of the original population chosen by
+    ///    `parent_selection`, with the probability given by `crossover_rate`.
     ///
-    /// 4. Merge the newly mutated population and the original cloned population into one big
-    /// population twice the size.
+    /// 4. Mutate the current population using the `mutate` method of each individual.
+    ///    Depending on `super_optimization` the intermediate states are evaluated as well.
     ///
-    /// 5. Sort this new big population by fitness. So the fittest individual is at position 0.
+    ///    If one of these steps panics, the individual is marked as invalid and discarded.
     ///
-    /// 6. Truncated the big population to its original size and thus gets rid of all the less fittest
-    /// individuals (they "die").
+    /// 5. Merge the newly mutated population and the original cloned population into one big
+    ///    population twice the size.
     ///
-    /// 7. Check if the fittest individual (at index 0) in the current sorted population is better
-    /// (= fitter) than the global fittest individual of the whole simulation. If yes, the global
-    /// fittest individual is replaced.
+    /// 6. Sort this new big population by fitness. So the fittest individual is at position 0.
+    ///    Individuals with equal fitness are sorted according to `tie_break`.
+    ///    For a multi-objective optimization the population is sorted by Pareto rank and
+    ///    crowding distance instead.
     ///
-    /// 8. Calculate the new improvement factor and prepare for the next iteration.
+    /// 7. Reduce the big population to its original size using the `selection` strategy and
+    ///    thus gets rid of (most of) the less fittest individuals (they "die"). The selected
+    ///    individuals are still sorted by fitness.
+    ///
+    /// 8. Restore the original mutation rates, since these are lost by sorting.
+    ///
+    /// 9. Adapt the penalty weight, if `constraint_handling` is `AdaptivePenalty`.
     pub fn run_body(&mut self) {
-        // Is reset limit enabled ?
-        if self.reset_limit_end > 0 {
-            self.reset_counter += 1;
+        let variation = self.begin_iteration();
+        let offspring = variation.vary_all(&mut self.population, 0, &variation.seeds, &variation.operators);
+        self.end_iteration(variation, vec![offspring]);
+    }
+
+    /// Step 1 and 2 of `run_body`. Returns everything that is needed for the recombination
+    /// and mutation, so that these can be split up into several jobs with `variation_jobs`.
+    pub(crate) fn begin_iteration(&mut self) -> Variation<T> {
+        // The random number generator is needed while the population is borrowed.
+        // It is stored back at the end of the iteration.
+        let mut rng = self.rng.clone();
+
+        if self.restart_reached() {
+            self.num_of_resets += 1;
+
+            let num_of_individuals = self.restart_policy.next_num_of_individuals(&mut self.restart_state,
+                self.num_of_individuals, self.num_of_evaluations, &mut rng).max(1);
+            info!("restart, id: {}, counter: {}, num_of_individuals: {}", self.id, self.fitness_counter, num_of_individuals);
+
+            // Grow the population with copies, they are reset anyway.
+            let old_len = self.population.len();
+            for index in old_len..num_of_individuals as usize {
+                let wrapper = self.population[index % old_len].clone();
+                self.population.push(wrapper);
+            }
+            self.population.truncate(num_of_individuals as usize);
+            self.num_of_individuals = num_of_individuals;
+
+            // Kill all individuals since we are most likely stuck in a local minimum.
+            // Why is it so ? Because the simulation is still running and the exit criteria
+            // hasn't been reached yet!
+            // Keep number of mutations.
+            // An individual whose reset panicked stays invalid and is discarded.
+            for wrapper in &mut self.population {
+                if wrapper.reset(&mut rng) {
+                    wrapper.calculate_fitness();
+                }
+            }
+            self.num_of_evaluations += self.population.len() as u64;
+            self.num_of_failures += self.population.iter().filter(|wrapper| wrapper.has_failed()).count() as u64;
+
+            // Start the adaptation of the mutation rate anew.
+            self.mutation_scale = 1.0;
+            self.num_of_successes = 0;
+            self.num_of_trials = 0;
+            self.adaptation_counter = 0;
+            self.restart_state.stagnation_counter = 0;
+            self.restart_state.iterations_since_restart = 0;
+        }
+
+        // Keep original population.
+        let orig_population = self.population.clone();
+
+        // The original population may not be sorted (after a reset or at the beginning),
+        // but the parent selection needs sorted fitness values.
+        let (parents, parents_fitness) = self.order_candidates(&orig_population, 0, &mut rng);
+
+        // Each individual gets its own random number generator, so the result does not depend
+        // on how the individuals are distributed over the threads.
+        let seeds = self.population.iter().map(|_| rng.gen()).collect();
+
+        if self.operator_statistics.is_empty() {
+            self.operator_statistics = OperatorStatistics::new(self.population[0].operators());
+        }
+
+        let operators = if self.operator_statistics.is_empty() {
+            vec![None; self.population.len()]
+        } else {
+            self.operator_selection.choose(&self.operator_statistics, self.population.len(), &mut rng)
+                .into_iter().map(Some).collect()
+        };
+
+        Variation {
+            direction: self.direction,
+            crossover_rate: self.crossover_rate,
+            parent_selection: self.parent_selection.clone(),
+            super_optimization: self.super_optimization.clone(),
+            mutation_adaptation: self.mutation_adaptation.clone(),
+            mutation_scale: self.mutation_scale,
+            parents,
+            parents_fitness,
+            orig_population,
+            seeds,
+            operators,
+            num_of_operators: self.operator_statistics.len(),
+            rng,
+        }
+    }
+
+    /// Splits step 3 and 4 of `run_body` into (at most) `num_of_chunks` jobs for an executor.
+    /// Each job stores its offspring in `offspring`, which are then passed to `end_iteration`.
+    pub(crate) fn variation_jobs<'a>(&'a mut self, variation: &'a Variation<T>, num_of_chunks: usize,
+        offspring: &'a mut Vec<Offspring<T>>) -> Vec<Job<'a>> {
+        let num_of_chunks = num_of_chunks.max(1);
+        let chunk_size = ((self.population.len() + num_of_chunks - 1) / num_of_chunks).max(1);
+        offspring.clear();
+        offspring.resize_with((self.population.len() + chunk_size - 1) / chunk_size, Offspring::default);
+
+        self.population.chunks_mut(chunk_size).enumerate().zip(variation.seeds.chunks(chunk_size))
+            .zip(variation.operators.chunks(chunk_size)).zip(offspring.iter_mut())
+            .map(|((((chunk, wrappers), seeds), operators), offspring)|
+                Box::new(move || *offspring = variation.vary_all(wrappers, chunk * chunk_size, seeds, operators)) as Job)
+            .collect()
+    }
+
+    /// Step 5 to 9 of `run_body`: selects the survivors of the offspring and the original
+    /// population.
+    pub(crate) fn end_iteration(&mut self, mut variation: Variation<T>, offspring: Vec<Offspring<T>>) {
+        let mut intermediates = Vec::new();
+        let mut num_of_successes = 0;
+        let mut operator_results = vec![(0, 0); variation.num_of_operators];
+
+        // Keep the order of the intermediate states.
+        for chunk in offspring {
+            intermediates.extend(chunk.intermediates);
+            self.num_of_evaluations += chunk.num_of_evaluations;
+            num_of_successes += chunk.num_of_successes;
+
+            for (total, result) in operator_results.iter_mut().zip(chunk.operator_results) {
+                total.0 += result.0;
+                total.1 += result.1;
+            }
+        }
+
+        self.operator_selection.update(&mut self.operator_statistics, &operator_results);
+
+        // Count the failed offspring and discard the invalid ones.
+        self.num_of_failures += self.population.iter().chain(intermediates.iter())
+            .filter(|wrapper| wrapper.has_failed()).count() as u64;
+        self.population.retain(|wrapper| wrapper.valid);
+        intermediates.retain(|wrapper| wrapper.valid);
+
+        // Intermediate states count as offspring.
+        self.population.extend(intermediates);
+
+        // Append original (unmutated) population to new (mutated) population.
+        let num_of_offspring = self.population.len();
+        self.population.extend(variation.orig_population.iter().cloned());
+
+        // Sort by fitness
+        let (order, fitness) = self.order_candidates(&self.population, num_of_offspring, &mut variation.rng);
+        self.take_candidates(&order);
+
+        // Reduce population to original length.
+        let selected = self.selection.select(&fitness, self.num_of_individuals as usize, &mut variation.rng);
+        self.take_candidates(&selected);
+
+        // Restore original number of mutation rate, since these will be lost because of sorting.
+        // Self-adaptive individuals keep their own one.
+        if let MutationAdaptation::SelfAdaptive{ .. } = self.mutation_adaptation {
+        } else {
+            for (individual, orig_individual) in self.population
+                .iter_mut()
+                .zip(variation.orig_population.iter()) {
+                individual.num_of_mutations = orig_individual.num_of_mutations;
+            }
+        }
+
+        self.adapt_mutation_scale(num_of_successes, variation.seeds.len() as u64);
+
+        // Has the fittest individual improved in this iteration ?
+        let fittest = &self.population[simulation::fittest_index(&self.population, self.direction)];
+        let orig_fittest = &variation.orig_population[simulation::fittest_index(&variation.orig_population, self.direction)];
+        if fittest.compare_fitness(orig_fittest, self.direction) == Ordering::Less {
+            self.restart_state.stagnation_counter = 0;
+        } else {
+            self.restart_state.stagnation_counter += 1;
+        }
+        self.restart_state.iterations_since_restart += 1;
+
+        if let ConstraintHandling::AdaptivePenalty{ ref mut weight, factor } = self.constraint_handling {
+            if self.population[0].is_feasible() {
+                *weight /= factor;
+            } else {
+                *weight *= factor;
+            }
+        }
+
+        self.rng = variation.rng;
+    }
+
+    /// Adapts the `mutation_scale` after an iteration according to the `mutation_adaptation`,
+    /// given the number of offspring and how many of them were fitter than their parent.
+    fn adapt_mutation_scale(&mut self, num_of_successes: u64, num_of_trials: u64) {
+        match self.mutation_adaptation {
+            MutationAdaptation::OneFifthRule{ factor, interval, max_mutations } => {
+                self.num_of_successes += num_of_successes;
+                self.num_of_trials += num_of_trials;
+                self.adaptation_counter += 1;
+
+                if self.adaptation_counter >= interval {
+                    let success_rate = self.num_of_successes as f64 / self.num_of_trials.max(1) as f64;
+
+                    if success_rate > 0.2 {
+                        self.mutation_scale *= factor;
+                    } else if success_rate < 0.2 {
+                        self.mutation_scale /= factor;
+                    }
+
+                    // Beyond these limits the scale would have no effect, but it would take
+                    // longer to come back.
+                    let max_mutations = max_mutations as f64;
+                    self.mutation_scale = self.mutation_scale.max(1.0 / max_mutations).min(max_mutations);
+                    self.num_of_successes = 0;
+                    self.num_of_trials = 0;
+                    self.adaptation_counter = 0;
+                }
+            }
+            MutationAdaptation::Decay{ factor } => {
+                self.mutation_scale *= factor;
+            }
+            MutationAdaptation::Fixed | MutationAdaptation::SelfAdaptive{ .. } => {}
+        }
+    }
+
+    /// The strategies of this population that could not be restored from a checkpoint, because
+    /// they are user defined: "selection", "parent_selection" or "tie_break".
+    pub(crate) fn unrestored_strategies(&self) -> Vec<&'static str> {
+        let mut strategies = Vec::new();
+
+        if self.selection.built_in().is_none() {
+            strategies.push("selection");
+        }
+        if self.parent_selection.built_in().is_none() {
+            strategies.push("parent_selection");
+        }
+        if let TieBreak::Custom(_) = self.tie_break {
+            strategies.push("tie_break");
+        }
+
+        strategies
+    }
+
+    /// Returns true if the population has to be restarted according to the `restart_trigger`.
+    /// Called once per iteration, since it also counts the iterations of the `ResetLimit`.
+    fn restart_reached(&mut self) -> bool {
+        match self.restart_trigger {
+            RestartTrigger::ResetLimit => {
+                // Is reset limit enabled ?
+                if self.reset_limit_end == 0 {
+                    return false;
+                }
+
+                self.reset_counter += 1;
+
+                // Check if reset limit is reached
+                if self.reset_counter <= self.reset_limit {
+                    return false;
+                }
 
-            // Check if reset limit is reached
-            if self.reset_counter > self.reset_limit {
                 self.reset_limit += self.reset_limit_increment;
                 if self.reset_limit >= self.reset_limit_end {
                     self.reset_limit = self.reset_limit_start;
@@ -96,46 +596,972 @@ impl<T: Individual + Send + Sync + Clone> Population<T> {
                 }
                 self.reset_counter = 0;
                 info!("new reset_limit: {}, id: {}, counter: {}", self.reset_limit, self.id, self.fitness_counter);
+                true
+            }
+            RestartTrigger::Stagnation{ iterations } => self.restart_state.stagnation_counter >= iterations,
+            RestartTrigger::Diversity{ threshold, min_iterations } => {
+                if self.restart_state.iterations_since_restart < min_iterations {
+                    return false;
+                }
+
+                let fittest = &self.population[simulation::fittest_index(&self.population, self.direction)];
+                let valid: Vec<&IndividualWrapper<T>> = self.population.iter().filter(|wrapper| wrapper.valid).collect();
+                let diversity = valid.iter().map(|wrapper| wrapper.distance(fittest)).sum::<f64>() / valid.len().max(1) as f64;
+                diversity < threshold
+            }
+        }
+    }
+
+    /// Sort the candidates (offspring and parents) by fitness, the fittest one comes first.
+    /// The first `num_of_offspring` candidates are the offspring, the rest are the parents.
+    /// Candidates with equal fitness are sorted according to `tie_break`.
+    /// Invalid candidates always come last, infeasible candidates are sorted according to
+    /// `constraint_handling`.
+    /// In a multi-objective optimization the candidates are sorted by their Pareto rank and
+    /// crowding distance instead (NSGA-II), only the feasible candidates are ranked.
+    /// Returns the sorted indices of the candidates and the values for the selection strategy:
+    /// the (penalized) fitness or the Pareto rank.
+    fn order_candidates(&self, candidates: &[IndividualWrapper<T>], num_of_offspring: usize,
+        rng: &mut DarwinRng) -> (Vec<usize>, Vec<f64>) {
+        let random_keys: Vec<u64> = match self.tie_break {
+            TieBreak::Random => candidates.iter().map(|_| rng.gen()).collect(),
+            _ => Vec::new(),
+        };
+
+        let (ranks, distance) = if self.multi_objective {
+            let feasible: Vec<usize> = (0..candidates.len()).filter(|index| candidates[*index].is_feasible()).collect();
+            let objectives: Vec<&[f64]> = feasible.iter().map(|index| &candidates[*index].objectives[..]).collect();
+            let feasible_ranks = pareto::non_dominated_sort(&objectives, self.direction);
+            let feasible_distance = pareto::crowding_distance(&objectives, &feasible_ranks);
 
-                // Kill all individuals since we are most likely stuck in a local minimum.
-                // Why is it so ? Because the simulation is still running and the exit criteria
-                // hasn't been reached yet!
-                // Keep number of mutations.
-                for wrapper in &mut self.population {
-                    wrapper.individual.reset();
-                    wrapper.fitness = wrapper.individual.calculate_fitness();
+            // Infeasible candidates are sorted by their constraint violation, they are
+            // ranked behind all the feasible ones.
+            let last_rank = feasible_ranks.iter().cloned().max().map_or(0, |rank| rank + 1);
+            let mut ranks = vec![last_rank; candidates.len()];
+            let mut distance = vec![0.0; candidates.len()];
+
+            for (position, index) in feasible.iter().enumerate() {
+                ranks[*index] = feasible_ranks[position];
+                distance[*index] = feasible_distance[position];
+            }
+
+            (ranks, distance)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        let penalty_weight = self.penalty_weight();
+        let mut order: Vec<usize> = (0..candidates.len()).collect();
+
+        order.sort_by(|&a, &b| {
+            let (wrapper_a, wrapper_b) = (&candidates[a], &candidates[b]);
+
+            let ordering = match penalty_weight {
+                Some(weight) => (!wrapper_a.valid).cmp(&!wrapper_b.valid).then_with(||
+                    self.direction.compare(&self.penalized_fitness(wrapper_a, weight), &self.penalized_fitness(wrapper_b, weight))),
+                None => wrapper_a.compare_feasibility(wrapper_b).then_with(|| if self.multi_objective {
+                    pareto::compare(ranks[a], distance[a], ranks[b], distance[b])
+                } else {
+                    self.direction.compare(&wrapper_a.fitness, &wrapper_b.fitness)
+                }),
+            };
+
+            ordering.then_with(|| match self.tie_break {
+                TieBreak::Random => random_keys[a].cmp(&random_keys[b]),
+                TieBreak::PreferOffspring => (a >= num_of_offspring).cmp(&(b >= num_of_offspring)),
+                TieBreak::PreferParent => (a < num_of_offspring).cmp(&(b < num_of_offspring)),
+                TieBreak::Custom(compare) => compare(&candidates[a].individual, &candidates[b].individual),
+            })
+        });
+
+        let mut values: Vec<f64> = if self.multi_objective {
+            order.iter().map(|index| ranks[*index] as f64).collect()
+        } else if let Some(weight) = penalty_weight {
+            order.iter().map(|index| self.penalized_fitness(&candidates[*index], weight)).collect()
+        } else {
+            order.iter().map(|index| candidates[*index].fitness.to_f64()).collect()
+        };
+
+        // Invalid candidates and NaN values come last, they get the value of the least fit
+        // valid candidate.
+        let mut last_value = 0.0;
+        for (value, index) in values.iter_mut().zip(order.iter()) {
+            if candidates[*index].has_failed() {
+                *value = last_value;
+            } else {
+                last_value = *value;
+            }
+        }
+
+        (order, values)
+    }
+
+    /// Returns the current penalty weight, if the constraints are handled by a penalty function.
+    fn penalty_weight(&self) -> Option<f64> {
+        match self.constraint_handling {
+            _ if self.multi_objective => None,
+            ConstraintHandling::FeasibilityFirst => None,
+            ConstraintHandling::Penalty{ weight } => Some(weight),
+            ConstraintHandling::AdaptivePenalty{ weight, .. } => Some(weight),
+        }
+    }
+
+    /// The fitness of a candidate, made worse by its weighted constraint violation.
+    fn penalized_fitness(&self, wrapper: &IndividualWrapper<T>, weight: f64) -> f64 {
+        let penalty = weight * wrapper.constraint_violation.max(0.0);
+
+        match self.direction {
+            Direction::Minimize => wrapper.fitness.to_f64() + penalty,
+            Direction::Maximize => wrapper.fitness.to_f64() - penalty,
+        }
+    }
+
+    /// Replace the current candidates with the ones given by the indices. The same index may
+    /// occur several times, but then these occurrences must be next to each other.
+    fn take_candidates(&mut self, indices: &[usize]) {
+        let mut candidates: Vec<Option<IndividualWrapper<T>>> =
+            self.population.drain(..).map(Some).collect();
+
+        for &index in indices {
+            // If a candidate has already been taken it is the last one in the new population.
+            let wrapper = match candidates[index].take() {
+                Some(wrapper) => wrapper,
+                None => self.population[self.population.len() - 1].clone(),
+            };
+            self.population.push(wrapper);
+        }
+    }
+}
+
+/// The result of the recombination and mutation of some individuals of a population.
+pub(crate) struct Offspring<T: Individual> {
+    /// The intermediate states that are kept (see `SuperOptimization`).
+    intermediates: Vec<IndividualWrapper<T>>,
+    /// The number of fitness evaluations.
+    num_of_evaluations: u64,
+    /// The number of individuals that are fitter than their parent.
+    num_of_successes: u64,
+    /// The number of applications and successes of each mutation operator.
+    operator_results: Vec<(u64, u64)>,
+}
+
+impl<T: Individual> Default for Offspring<T> {
+    fn default() -> Offspring<T> {
+        Offspring {
+            intermediates: Vec::new(),
+            num_of_evaluations: 0,
+            num_of_successes: 0,
+            operator_results: Vec::new(),
+        }
+    }
+}
+
+/// Everything that is needed for the recombination and mutation of one iteration. It is
+/// shared by all individuals of the population, so the individuals can be varied in parallel.
+pub(crate) struct Variation<T: Individual> {
+    direction: Direction,
+    crossover_rate: f64,
+    parent_selection: Arc<dyn Selection>,
+    super_optimization: SuperOptimization,
+    mutation_adaptation: MutationAdaptation,
+    mutation_scale: f64,
+    /// The indices of the parents in `orig_population`, sorted by fitness.
+    parents: Vec<usize>,
+    /// The fitness values of the parents for the `parent_selection`.
+    parents_fitness: Vec<f64>,
+    /// The population before the recombination and mutation.
+    orig_population: Vec<IndividualWrapper<T>>,
+    /// The seeds for the random number generators of the individuals.
+    seeds: Vec<u64>,
+    /// The mutation operators of the individuals, `None` if they have no operators.
+    operators: Vec<Option<usize>>,
+    num_of_operators: usize,
+    /// The random number generator of the population.
+    rng: DarwinRng,
+}
+
+impl<T: Individual + Send + Sync + Clone> Variation<T> {
+    /// Recombines and mutates the given individuals, each one with the random number generator
+    /// created from its seed. `first_index` is the index of the first one in the population.
+    fn vary_all(&self, wrappers: &mut [IndividualWrapper<T>], first_index: usize, seeds: &[u64],
+        operators: &[Option<usize>]) -> Offspring<T> {
+        let mut intermediates = Vec::new();
+        let mut num_of_evaluations = 0;
+        let mut num_of_successes = 0;
+        let mut operator_results = vec![(0, 0); self.num_of_operators];
+
+        for (((index, wrapper), seed), operator) in wrappers.iter_mut().enumerate().zip(seeds).zip(operators) {
+            let mut rng = DarwinRng::from_seed(*seed);
+            let parent_fitness = wrapper.fitness.clone();
+
+            // Catch a panic of the user code, so that the other individuals and populations
+            // are not affected.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let num_of_mutations = self.num_of_mutations(wrapper, &mut rng);
+
+                if self.crossover_rate > 0.0 && rng.gen::<f64>() < self.crossover_rate {
+                    let partner = self.select_partner(first_index + index, &mut rng);
+                    wrapper.individual.crossover(&self.orig_population[partner].individual, &mut rng);
                 }
+
+                if self.super_optimization == SuperOptimization::Off || num_of_mutations < 2 {
+                    for _ in 0..num_of_mutations {
+                        mutate(&mut wrapper.individual, *operator, &mut rng);
+                    }
+                    wrapper.calculate_fitness();
+                    num_of_evaluations += 1;
+                    return;
+                }
+
+                // Evaluate each intermediate state
+                let mut best: Option<IndividualWrapper<T>> = None;
+
+                for step in 1..(num_of_mutations + 1) {
+                    mutate(&mut wrapper.individual, *operator, &mut rng);
+                    wrapper.calculate_fitness();
+                    num_of_evaluations += 1;
+
+                    if step == num_of_mutations {
+                        break;
+                    }
+
+                    // A state whose fitness calculation has panicked still has the fitness of
+                    // the previous state, it is never the best one.
+                    if self.super_optimization == SuperOptimization::KeepAll {
+                        intermediates.push(wrapper.clone());
+                    } else if wrapper.valid && best.as_ref().map_or(true, |best| !self.direction.is_better(&best.fitness, &wrapper.fitness)) {
+                        best = Some(wrapper.clone());
+                    }
+                }
+
+                if let Some(best) = best {
+                    if !wrapper.valid || self.direction.is_better(&best.fitness, &wrapper.fitness) {
+                        *wrapper = best;
+                    }
+                }
+            }));
+
+            let success = result.is_ok() && self.direction.is_better(&wrapper.fitness, &parent_fitness);
+
+            if result.is_err() {
+                wrapper.valid = false;
+            } else if success {
+                num_of_successes += 1;
+            }
+
+            if let Some(operator) = *operator {
+                operator_results[operator].0 += 1;
+                operator_results[operator].1 += success as u64;
             }
         }
 
-        // Keep original population.
-        let orig_population = self.population.clone();
+        Offspring {
+            intermediates,
+            num_of_evaluations,
+            num_of_successes,
+            operator_results,
+        }
+    }
 
-        // Mutate population
-        for wrapper in &mut self.population {
-            for _ in 0..wrapper.num_of_mutations {
-                // Maybe add super optimization ?
-                // See https://github.com/willi-kappler/darwin-rs/issues/10
-                wrapper.individual.mutate();
+    /// Selects the crossover partner of the individual with the given index with the
+    /// `parent_selection`. The individual itself is never chosen. Returns the index of the
+    /// partner in the original population.
+    fn select_partner(&self, index: usize, rng: &mut DarwinRng) -> usize {
+        let own_position = self.parents.iter().position(|parent| *parent == index).unwrap();
+        let fitness: Vec<f64> = self.parents_fitness.iter().enumerate()
+            .filter(|&(position, _)| position != own_position)
+            .map(|(_, fitness)| *fitness).collect();
+
+        let position = self.parent_selection.select(&fitness, 1, rng)[0];
+        self.parents[if position < own_position { position } else { position + 1 }]
+    }
+
+    /// The number of mutations of the given individual in this iteration, according to the
+    /// `mutation_adaptation`. A self-adaptive individual changes its own number first.
+    fn num_of_mutations(&self, wrapper: &mut IndividualWrapper<T>, rng: &mut DarwinRng) -> u32 {
+        let scaled = (wrapper.num_of_mutations as f64 * self.mutation_scale).round().max(1.0);
+
+        match self.mutation_adaptation {
+            MutationAdaptation::Fixed => wrapper.num_of_mutations,
+            MutationAdaptation::OneFifthRule{ max_mutations, .. } => (scaled as u32).min(max_mutations),
+            MutationAdaptation::Decay{ .. } => scaled as u32,
+            MutationAdaptation::SelfAdaptive{ factor, max_mutations } => {
+                let num_of_mutations = wrapper.num_of_mutations as f64;
+                let num_of_mutations = match rng.gen_range(0, 3) {
+                    0 => (num_of_mutations * factor).ceil(),
+                    1 => (num_of_mutations / factor).floor(),
+                    _ => num_of_mutations,
+                };
+
+                wrapper.num_of_mutations = (num_of_mutations.max(1.0) as u32).min(max_mutations);
+                wrapper.num_of_mutations
             }
-            wrapper.fitness = wrapper.individual.calculate_fitness();
         }
+    }
+}
 
-        // Append original (unmutated) population to new (mutated) population.
-        self.population.extend(orig_population.iter().cloned());
+/// Mutates the individual with the given operator, or with `mutate` if it has no operators.
+fn mutate<T: Individual>(individual: &mut T, operator: Option<usize>, rng: &mut DarwinRng) {
+    match operator {
+        Some(operator) => individual.apply_operator(operator, rng),
+        None => individual.mutate(rng),
+    }
+}
 
-        // Sort by fitness
-        // Use random choice, see https://github.com/willi-kappler/darwin-rs/issues/7
-        self.population.sort();
+#[cfg(test)]
+mod test {
+    use super::{Population, TieBreak, SuperOptimization, ConstraintHandling, MutationAdaptation};
+    use operator::OperatorSelection;
+    use individual::Individual;
+    use population_builder::PopulationBuilder;
+    use random::DarwinRng;
+    use restart::{RestartTrigger, RestartPolicy};
+    use selection::{Selection, Truncation};
+    use simulation::Direction;
 
-        // Reduce population to original length.
-        self.population.truncate(self.num_of_individuals as usize);
+    #[derive(Clone)]
+    struct IndividualTest1 {
+        crossed: bool,
+    }
 
-        // Restore original number of mutation rate, since these will be lost because of sorting.
-        for (individual, orig_individual) in self.population
-            .iter_mut()
-            .zip(orig_population.iter()) {
-            individual.num_of_mutations = orig_individual.num_of_mutations;
+    impl Individual for IndividualTest1 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn crossover(&mut self, _other: &IndividualTest1, _rng: &mut DarwinRng) {
+            self.crossed = true;
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            if self.crossed { 0.0 } else { 1.0 }
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.crossed = false;
         }
     }
+
+    #[derive(Clone)]
+    struct IndividualTest2 {
+        value: i32,
+    }
+
+    impl Individual for IndividualTest2 {
+        type Fitness = i32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value += 1;
+        }
+
+        fn calculate_fitness(&mut self) -> i32 {
+            (self.value - 2).abs()
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 0;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest3 {
+        value: f64,
+    }
+
+    impl Individual for IndividualTest3 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value += 1.0;
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            if self.value == 1.0 {
+                panic!("numerical code blew up");
+            } else if self.value == 2.0 {
+                f64::NAN
+            } else {
+                self.value
+            }
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 0.0;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest4 {
+        value: i32,
+        repairable: bool,
+    }
+
+    impl Individual for IndividualTest4 {
+        type Fitness = i32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value -= 2;
+        }
+
+        fn calculate_fitness(&mut self) -> i32 {
+            self.value
+        }
+
+        // Values below 2 are infeasible.
+        fn constraint_violation(&self) -> f64 {
+            (2 - self.value).max(0) as f64
+        }
+
+        fn repair(&mut self) {
+            if self.repairable {
+                self.value = self.value.max(2);
+            }
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 3;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest5 {
+        value: f64,
+    }
+
+    impl Individual for IndividualTest5 {
+        type Fitness = f64;
+
+        // Each mutation is an improvement.
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value -= 1.0;
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            self.value
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 1000.0;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest6 {
+        value: f64,
+    }
+
+    impl Individual for IndividualTest6 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn operators(&self) -> &'static [&'static str] {
+            &["worse", "better"]
+        }
+
+        fn apply_operator(&mut self, operator: usize, _rng: &mut DarwinRng) {
+            self.value += if operator == 0 { 1.0 } else { -1.0 };
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            self.value
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 1000.0;
+        }
+    }
+
+    /// Selects the least fit candidates, the opposite of `Truncation`.
+    struct Last;
+
+    impl Selection for Last {
+        fn select(&self, fitness: &[f64], num_of_selected: usize, _rng: &mut DarwinRng) -> Vec<usize> {
+            (fitness.len() - num_of_selected.min(fitness.len())..fitness.len()).collect()
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest7 {
+        id: u32,
+        partner: Option<u32>,
+    }
+
+    impl Individual for IndividualTest7 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn crossover(&mut self, other: &IndividualTest7, _rng: &mut DarwinRng) {
+            self.partner = Some(other.id);
+        }
+
+        // The offspring of a crossover are always fitter than the parents.
+        fn calculate_fitness(&mut self) -> f64 {
+            self.id as f64 - if self.partner.is_some() { 10.0 } else { 0.0 }
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.partner = None;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest8 {
+        steps: u32,
+    }
+
+    impl Individual for IndividualTest8 {
+        type Fitness = f64;
+
+        // A plateau: the individual changes, but the fitness stays the same.
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.steps += 1;
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            0.0
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.steps = 0;
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest9 {
+        value: i32,
+    }
+
+    impl Individual for IndividualTest9 {
+        type Fitness = i32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn calculate_fitness(&mut self) -> i32 {
+            0
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            if self.value % 2 == 1 {
+                panic!("reset blew up");
+            }
+        }
+
+        fn operators(&self) -> &'static [&'static str] {
+            panic!("operators blew up");
+        }
+
+        fn distance(&self, _other: &Self) -> Option<f64> {
+            panic!("distance blew up");
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest10 {
+        value: i32,
+        spread: f64,
+    }
+
+    impl Individual for IndividualTest10 {
+        type Fitness = i32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn calculate_fitness(&mut self) -> i32 {
+            self.value
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn distance(&self, _other: &Self) -> Option<f64> {
+            Some(self.spread)
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest11 {
+        value: i32,
+    }
+
+    impl Individual for IndividualTest11 {
+        type Fitness = i32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value += 1;
+        }
+
+        fn calculate_fitness(&mut self) -> i32 {
+            if self.value == 3 {
+                panic!("calculate_fitness failed");
+            }
+            (self.value - 2).abs()
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 0;
+        }
+    }
+
+    /// Creates a population with the given individuals and options, without the fixed reset
+    /// schedule, and calculates the fitness of the individuals.
+    fn make_population<T, F>(individuals: &[T], options: F) -> Population<T>
+        where T: Individual + Send + Sync + Clone, F: FnOnce(PopulationBuilder<T>) -> PopulationBuilder<T> {
+        let mut population = options(PopulationBuilder::<T>::new()
+            .initial_population(individuals)
+            .reset_limit_end(0))
+            .finalize().unwrap();
+        population.calculate_fitness();
+        population
+    }
+
+    #[test]
+    fn crossover1() {
+        let mut population = make_population(&vec![IndividualTest1 { crossed: false }; 5], |builder| builder.crossover_rate(1.0));
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper| wrapper.individual.crossed));
+    }
+
+    #[test]
+    fn crossover2() {
+        let mut population = make_population(&vec![IndividualTest1 { crossed: false }; 5], |builder| builder);
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper| !wrapper.individual.crossed));
+    }
+
+    #[test]
+    fn crossover3() {
+        let individuals: Vec<IndividualTest7> = (0..5).map(|id| IndividualTest7 { id, partner: None }).collect();
+        // Truncation always selects the first candidate, so it would be its own partner.
+        let mut population = make_population(&individuals, |builder| builder
+            .crossover_rate(1.0)
+            .parent_selection(Truncation));
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper|
+            wrapper.individual.partner.is_some() && wrapper.individual.partner != Some(wrapper.individual.id)));
+    }
+
+    #[test]
+    fn selection1() {
+        // The offspring are fitter than their parents, but the parents survive.
+        let mut population = make_population(&vec![IndividualTest2 { value: 0 }; 4], |builder| builder
+            .selection(Last));
+        population.run_body();
+        assert!(population.population.iter().all(|wrapper| wrapper.fitness == 2));
+
+        // The least fit individual (id 4) is the partner of all the other ones.
+        let individuals: Vec<IndividualTest7> = (0..5).map(|id| IndividualTest7 { id, partner: None }).collect();
+        let mut population = make_population(&individuals, |builder| builder
+            .crossover_rate(1.0)
+            .parent_selection(Last));
+        population.run_body();
+        assert!(population.population.iter().all(|wrapper|
+            wrapper.individual.partner == Some(if wrapper.individual.id == 4 { 3 } else { 4 })));
+    }
+
+    #[test]
+    fn tie_break1() {
+        let mut population = make_population(&vec![IndividualTest1 { crossed: false }; 5], |builder| builder);
+        population.tie_break = TieBreak::Custom(|a, b| b.crossed.cmp(&a.crossed));
+        // Only change the individual, the fitness stays the same.
+        population.population[4].individual.crossed = true;
+        let (order, _) = population.order_candidates(&population.population, 5, &mut DarwinRng::from_seed(1));
+
+        assert_eq!(order[0], 4);
+    }
+
+    #[test]
+    fn tie_break2() {
+        // Returns the mean number of steps the individuals have drifted on the plateau.
+        let drift = |tie_break: TieBreak<IndividualTest8>| {
+            let mut population = make_population(&vec![IndividualTest8 { steps: 0 }; 10], |builder| builder
+                .tie_break(tie_break));
+            population.rng = DarwinRng::from_seed(1);
+
+            for _ in 0..10 {
+                population.run_body();
+            }
+
+            population.population.iter().map(|wrapper| wrapper.individual.steps as f64).sum::<f64>() / 10.0
+        };
+
+        let offspring = drift(TieBreak::PreferOffspring);
+        let random = drift(TieBreak::Random);
+        let parent = drift(TieBreak::PreferParent);
+
+        // The offspring always win and move on, the parents never make a step.
+        assert_eq!(offspring, 10.0);
+        assert_eq!(parent, 0.0);
+        assert!(random > 0.0 && random < offspring);
+    }
+
+    #[test]
+    fn super_optimization1() {
+        let mut population = make_population(&vec![IndividualTest2 { value: 0 }; 3], |builder| builder
+            .mutation_rate(vec![5, 5, 5])
+            .super_optimization(SuperOptimization::KeepBest));
+        population.run_body();
+
+        assert_eq!(population.population[0].individual.value, 2);
+        assert_eq!(population.population[0].fitness, 0);
+    }
+
+    #[test]
+    fn super_optimization2() {
+        let mut population = make_population(&vec![IndividualTest2 { value: 0 }; 3], |builder| builder
+            .mutation_rate(vec![5, 5, 5]));
+        population.run_body();
+
+        // Without super optimization the original individuals are still the fittest.
+        assert_eq!(population.population[0].individual.value, 0);
+    }
+
+    #[test]
+    fn super_optimization4() {
+        // The third state panics and keeps the fitness 0 of the second state, it must not be
+        // chosen.
+        let mut population = make_population(&vec![IndividualTest11 { value: 0 }; 3], |builder| builder
+            .mutation_rate(vec![5, 5, 5])
+            .super_optimization(SuperOptimization::KeepBest));
+        population.run_body();
+
+        assert_eq!(population.population[0].individual.value, 2);
+        assert_eq!(population.population[0].fitness, 0);
+        assert!(population.population[0].valid);
+    }
+
+    #[test]
+    fn super_optimization3() {
+        let run = |super_optimization: SuperOptimization| {
+            let mut population = make_population(&vec![IndividualTest5 { value: 1000.0 }; 3], |builder| builder
+                .mutation_rate(vec![3, 1, 1])
+                .super_optimization(super_optimization));
+            population.run_body();
+            population
+        };
+
+        // The intermediate states 999 and 998 of the first individual compete with the offspring.
+        let population = run(SuperOptimization::KeepAll);
+        let values: Vec<f64> = population.population.iter().map(|wrapper| wrapper.individual.value).collect();
+        assert_eq!(values, vec![997.0, 998.0, 999.0]);
+        // 3 initial evaluations, 3 steps of the first individual and one of each other one.
+        assert_eq!(population.num_of_evaluations, 8);
+
+        let population = run(SuperOptimization::KeepBest);
+        let values: Vec<f64> = population.population.iter().map(|wrapper| wrapper.individual.value).collect();
+        assert_eq!(values, vec![997.0, 999.0, 999.0]);
+        assert_eq!(population.num_of_evaluations, 8);
+    }
+
+    #[test]
+    fn failures1() {
+        let mut population = make_population(&vec![IndividualTest3 { value: 0.0 }; 3], |builder| builder
+            .mutation_rate(vec![1, 2, 3]));
+        population.direction = Direction::Maximize;
+        population.run_body();
+
+        // The panicking individual is discarded, the NaN individual is the least fit one.
+        assert_eq!(population.num_of_failures, 2);
+        assert_eq!(population.population.len(), 3);
+        assert_eq!(population.population[0].fitness, 3.0);
+        assert!(population.population.iter().all(|wrapper| !wrapper.has_failed()));
+    }
+
+    #[test]
+    fn failures2() {
+        // All individuals have the same fitness, the panicking distance falls back to the
+        // difference of the fitness values, so there is no diversity.
+        let mut population = make_population(&(0..4).map(|value| IndividualTest9 { value }).collect::<Vec<_>>(), |builder| builder
+            .restart_trigger(RestartTrigger::Diversity{ threshold: 0.5, min_iterations: 2 }));
+
+        for _ in 0..3 {
+            population.run_body();
+        }
+
+        // The individuals whose reset panicked are invalid and not evaluated again.
+        assert_eq!(population.num_of_resets, 1);
+        assert_eq!(population.num_of_failures, 2);
+        assert!(population.operator_statistics.is_empty());
+        assert_eq!(population.population.len(), 4);
+        assert!(population.population.iter().filter(|wrapper| wrapper.valid).count() >= 2);
+    }
+
+    #[test]
+    fn constraint1() {
+        // The offspring have a better fitness (1) but are infeasible.
+        let mut population = make_population(&vec![IndividualTest4 { value: 3, repairable: false }; 3], |builder| builder
+            .constraint_handling(ConstraintHandling::FeasibilityFirst));
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper| wrapper.individual.value == 3));
+    }
+
+    #[test]
+    fn constraint2() {
+        // The penalized fitness of the offspring is 1 + 0.5 * 1 = 1.5, still better than 3.
+        let mut population = make_population(&vec![IndividualTest4 { value: 3, repairable: false }; 3], |builder| builder
+            .constraint_handling(ConstraintHandling::AdaptivePenalty{ weight: 0.5, factor: 2.0 }));
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper| wrapper.individual.value == 1));
+        // The fittest individual is infeasible, so the weight increases.
+        assert_eq!(population.constraint_handling, ConstraintHandling::AdaptivePenalty{ weight: 1.0, factor: 2.0 });
+    }
+
+    #[test]
+    fn repair1() {
+        let mut population = make_population(&vec![IndividualTest4 { value: 3, repairable: true }; 3], |builder| builder
+            .constraint_handling(ConstraintHandling::FeasibilityFirst));
+        population.run_body();
+
+        assert!(population.population.iter().all(|wrapper| wrapper.individual.value == 2 && wrapper.is_feasible()));
+    }
+
+    #[test]
+    fn crossover_rate_invalid() {
+        let result = PopulationBuilder::<IndividualTest1>::new()
+            .initial_population(&vec![IndividualTest1 { crossed: false }; 5])
+            .crossover_rate(1.5)
+            .finalize();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn mutation_adaptation1() {
+        let mut population = make_population(&vec![IndividualTest5 { value: 1000.0 }; 4], |builder| builder
+            .mutation_adaptation(MutationAdaptation::OneFifthRule{ factor: 2.0, interval: 1, max_mutations: 8 }));
+        population.rng = DarwinRng::from_seed(1);
+        population.run_body();
+        assert_eq!(population.mutation_scale, 2.0);
+        assert_eq!(population.population[0].individual.value, 999.0);
+
+        // All offspring are fitter than their parent, so they mutate twice now.
+        population.run_body();
+        assert_eq!(population.population[0].individual.value, 997.0);
+
+        for _ in 0..5 {
+            population.run_body();
+        }
+        assert_eq!(population.mutation_scale, 8.0);
+        assert!(population.population.iter().all(|wrapper| wrapper.num_of_mutations == 1));
+    }
+
+    #[test]
+    fn mutation_adaptation2() {
+        let mut population = make_population(&vec![IndividualTest5 { value: 1000.0 }; 4], |builder| builder
+            .mutation_adaptation(MutationAdaptation::Decay{ factor: 0.5 }));
+        population.rng = DarwinRng::from_seed(1);
+        for _ in 0..3 {
+            population.run_body();
+        }
+        assert_eq!(population.mutation_scale, 0.125);
+        assert_eq!(population.population[0].individual.value, 997.0);
+    }
+
+    #[test]
+    fn mutation_adaptation3() {
+        let mut population = make_population(&vec![IndividualTest5 { value: 1000.0 }; 4], |builder| builder
+            .mutation_adaptation(MutationAdaptation::SelfAdaptive{ factor: 2.0, max_mutations: 4 }));
+        population.rng = DarwinRng::from_seed(1);
+        for _ in 0..20 {
+            population.run_body();
+        }
+
+        // Individuals that mutate more often are fitter, so they take over the population.
+        assert!(population.population.iter().all(|wrapper| (1..=4).contains(&wrapper.num_of_mutations)));
+        assert_eq!(population.population[0].num_of_mutations, 4);
+        assert_eq!(population.mutation_scale, 1.0);
+
+        assert!(PopulationBuilder::<IndividualTest5>::new()
+            .initial_population(&vec![IndividualTest5 { value: 1000.0 }; 4])
+            .mutation_adaptation(MutationAdaptation::SelfAdaptive{ factor: 1.0, max_mutations: 4 })
+            .finalize().is_err());
+    }
+
+    #[test]
+    fn operator_selection1() {
+        for operator_selection in [
+            OperatorSelection::ProbabilityMatching{ min_probability: 0.05, adaptation_rate: 0.5 },
+            OperatorSelection::AdaptivePursuit{ min_probability: 0.05, adaptation_rate: 0.5, learning_rate: 0.5 },
+            OperatorSelection::Bandit{ exploration: 0.5 }] {
+            let mut population = make_population(&vec![IndividualTest6 { value: 1000.0 }; 10], |builder| builder
+                .operator_selection(operator_selection));
+            population.rng = DarwinRng::from_seed(1);
+
+            for _ in 0..20 {
+                population.run_body();
+            }
+
+            let statistics = &population.operator_statistics;
+            assert_eq!(statistics[0].name, "worse");
+            assert_eq!(statistics[0].num_of_successes, 0);
+            assert_eq!(statistics[1].num_of_successes, statistics[1].num_of_applications);
+            assert_eq!(statistics[0].num_of_applications + statistics[1].num_of_applications, 200);
+            assert!(statistics[1].num_of_applications > 150);
+        }
+    }
+
+    #[test]
+    fn restart1() {
+        // The individuals start at the optimum, so the population stagnates from the beginning.
+        let mut population = make_population(&vec![IndividualTest2 { value: 2 }; 4], |builder| builder
+            .restart_trigger(RestartTrigger::Stagnation{ iterations: 3 })
+            .restart_policy(RestartPolicy::IncreasingPopulation{ factor: 2.0, max_individuals: 10 }));
+
+        for _ in 0..3 {
+            population.run_body();
+        }
+        assert_eq!(population.num_of_resets, 0);
+        assert_eq!(population.restart_state.stagnation_counter, 3);
+
+        population.run_body();
+        assert_eq!(population.num_of_resets, 1);
+        assert_eq!(population.num_of_individuals, 8);
+        assert_eq!(population.population.len(), 8);
+        assert_eq!(population.restart_state.stagnation_counter, 0);
+
+        // All individuals have the same fitness, so there is no diversity.
+        let mut population = make_population(&vec![IndividualTest1 { crossed: false }; 5], |builder| builder
+            .restart_trigger(RestartTrigger::Diversity{ threshold: 0.5, min_iterations: 2 }));
+
+        for _ in 0..3 {
+            population.run_body();
+        }
+        assert_eq!(population.num_of_resets, 1);
+        assert_eq!(population.num_of_individuals, 5);
+
+        assert!(PopulationBuilder::<IndividualTest1>::new()
+            .initial_population(&vec![IndividualTest1 { crossed: false }; 5])
+            .restart_policy(RestartPolicy::IncreasingPopulation{ factor: 2.0, max_individuals: 4 })
+            .finalize().is_err());
+    }
+
+    #[test]
+    fn restart2() {
+        let run = |values: &[i32], spread: f64| {
+            let individuals: Vec<IndividualTest10> = values.iter().map(|value| IndividualTest10 { value: *value, spread }).collect();
+            let mut population = make_population(&individuals, |builder| builder
+                .restart_trigger(RestartTrigger::Diversity{ threshold: 0.5, min_iterations: 2 }));
+
+            for _ in 0..3 {
+                population.run_body();
+            }
+            population.num_of_resets
+        };
+
+        // The fitness values are different, but the individuals are at the same position.
+        assert_eq!(run(&[0, 10, 20, 30, 40], 0.0), 1);
+        // The fitness values are the same, but the individuals are spread out.
+        assert_eq!(run(&[5, 5, 5, 5, 5], 1.0), 0);
+    }
 }
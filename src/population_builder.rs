@@ -13,10 +13,17 @@
 //!
 //!
 
-use std;
+use std::sync::Arc;
 
 use individual::{Individual, IndividualWrapper};
-use population::Population;
+use migration::MigrationPolicy;
+use operator::OperatorSelection;
+use population::{Population, TieBreak, SuperOptimization, ConstraintHandling, MutationAdaptation,
+    default_selection, default_parent_selection, default_tie_break};
+use random::DarwinRng;
+use restart::{RestartTrigger, RestartPolicy, RestartState};
+use selection::Selection;
+use simulation::Direction;
 
 /// This is a helper struct in order to build (configure) a valid population.
 /// See builder pattern: https://en.wikipedia.org/wiki/Builder_pattern
@@ -31,6 +38,18 @@ error_chain! {
     errors {
         IndividualsTooLow
         LimitEndTooLow
+        CrossoverRateInvalid
+        PenaltyInvalid
+        MigrationPolicyInvalid
+        MutationAdaptationInvalid
+        OperatorSelectionInvalid
+        RestartInvalid
+    }
+}
+
+impl<T: Individual + Clone> Default for PopulationBuilder<T> {
+    fn default() -> PopulationBuilder<T> {
+        PopulationBuilder::new()
     }
 }
 
@@ -49,7 +68,31 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
                 reset_limit_increment: 1000,
                 reset_counter: 0,
                 id: 1,
-                fitness_counter: 0
+                crossover_rate: 0.0,
+                selection: default_selection(),
+                parent_selection: default_parent_selection(),
+                direction: Direction::Minimize,
+                multi_objective: false,
+                tie_break: default_tie_break(),
+                super_optimization: SuperOptimization::Off,
+                constraint_handling: ConstraintHandling::FeasibilityFirst,
+                rng: DarwinRng::from_entropy(),
+                fitness_counter: 0,
+                num_of_failures: 0,
+                num_of_evaluations: 0,
+                num_of_resets: 0,
+                num_of_migrations: 0,
+                migration_policy: None,
+                mutation_adaptation: MutationAdaptation::Fixed,
+                mutation_scale: 1.0,
+                num_of_successes: 0,
+                num_of_trials: 0,
+                adaptation_counter: 0,
+                operator_selection: OperatorSelection::Uniform,
+                operator_statistics: Vec::new(),
+                restart_trigger: RestartTrigger::ResetLimit,
+                restart_policy: RestartPolicy::Reset,
+                restart_state: RestartState::default(),
             }
         }
     }
@@ -61,9 +104,12 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
         for individual in individuals {
             self.population.population.push(IndividualWrapper {
                 individual: (*individual).clone(),
-                fitness: std::f64::MAX,
+                fitness: Default::default(),
                 num_of_mutations: 1,
                 id: self.population.id,
+                objectives: Vec::new(),
+                constraint_violation: 0.0,
+                valid: true,
             });
         }
 
@@ -74,11 +120,8 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
     /// in the population: The first individual will mutate once, the second will mutate twice,
     /// the nth individual will Mutate n-times per iteration.
     pub fn increasing_mutation_rate(mut self) -> PopulationBuilder<T> {
-        let mut mutation_rate = 1;
-
-        for wrapper in &mut self.population.population {
-            wrapper.num_of_mutations = mutation_rate;
-            mutation_rate += 1;
+        for (mutation_rate, wrapper) in self.population.population.iter_mut().enumerate() {
+            wrapper.num_of_mutations = mutation_rate as u32 + 1;
         }
 
         self
@@ -90,11 +133,8 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
     /// all the individuals. The first individual will mutate base^1 times, the second will
     /// mutate base^2 times, and nth will mutate base^n times per iteration.
     pub fn increasing_exp_mutation_rate(mut self, base: f64) -> PopulationBuilder<T> {
-        let mut mutation_rate = 1;
-
-        for wrapper in &mut self.population.population {
-            wrapper.num_of_mutations = base.powi(mutation_rate).floor() as u32;
-            mutation_rate += 1;
+        for (mutation_rate, wrapper) in self.population.population.iter_mut().enumerate() {
+            wrapper.num_of_mutations = base.powi(mutation_rate as i32 + 1).floor() as u32;
         }
 
         self
@@ -110,13 +150,30 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
         for (individual, mutation_rate) in self.population
             .population
             .iter_mut()
-            .zip(mutation_rate.into_iter()) {
+            .zip(mutation_rate) {
             individual.num_of_mutations = mutation_rate;
         }
 
         self
     }
 
+    /// Configures how the number of mutations adapts while the simulation is running: with the
+    /// 1/5th success rule (`OneFifthRule`), inherited by the offspring (`SelfAdaptive`) or
+    /// decaying over time (`Decay`). The mutation rates set above are the starting values.
+    /// Default is `Fixed`.
+    pub fn mutation_adaptation(mut self, mutation_adaptation: MutationAdaptation) -> PopulationBuilder<T> {
+        self.population.mutation_adaptation = mutation_adaptation;
+        self
+    }
+
+    /// Configures how the mutation operators of the individuals (see `operators` in the
+    /// `Individual` trait) are chosen: `ProbabilityMatching`, `AdaptivePursuit` or `Bandit`
+    /// prefer the operators that often create fitter offspring. Default is `Uniform`.
+    pub fn operator_selection(mut self, operator_selection: OperatorSelection) -> PopulationBuilder<T> {
+        self.population.operator_selection = operator_selection;
+        self
+    }
+
     /// Configures the reset limit for the population. If reset_limit_end is greater than zero
     /// then a reset counter is increased each iteration. If that counter is greater than the
     /// limit, all individuals will be resetted, the limit will be increased by 1000 and the
@@ -143,6 +200,80 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
         self
     }
 
+    /// Configures when all individuals are reset: after the fixed schedule of the reset limit
+    /// (`ResetLimit`), if the fittest individual has not improved for a number of iterations
+    /// (`Stagnation`) or if the diversity of the population is too low (`Diversity`).
+    /// The reset limit is only used by `ResetLimit`. Default is `ResetLimit`.
+    pub fn restart_trigger(mut self, restart_trigger: RestartTrigger) -> PopulationBuilder<T> {
+        self.population.restart_trigger = restart_trigger;
+        self
+    }
+
+    /// Configures the size of the population after a restart: it stays the same (`Reset`),
+    /// it grows (`IncreasingPopulation`, IPOP) or it alternates between large and small
+    /// populations (`BiPopulation`, BIPOP). Default is `Reset`.
+    pub fn restart_policy(mut self, restart_policy: RestartPolicy) -> PopulationBuilder<T> {
+        self.population.restart_policy = restart_policy;
+        self
+    }
+
+    /// Configures the crossover rate: the probability (0.0 - 1.0) that an individual is
+    /// recombined with a randomly chosen partner of the same population (using the `crossover`
+    /// method of the `Individual` trait) before it gets mutated. Default value is 0.0, which
+    /// disables crossover.
+    pub fn crossover_rate(mut self, crossover_rate: f64) -> PopulationBuilder<T> {
+        self.population.crossover_rate = crossover_rate;
+        self
+    }
+
+    /// Configures the strategy that selects the individuals surviving an iteration, for example
+    /// `Tournament` or `RouletteWheel`. Default is `Truncation`: only the fittest individuals
+    /// survive, which has the highest selection pressure.
+    pub fn selection<S: Selection + 'static>(mut self, selection: S) -> PopulationBuilder<T> {
+        self.population.selection = Arc::new(selection);
+        self
+    }
+
+    /// Configures the strategy that selects the partner for a crossover. This is only used
+    /// if the `crossover_rate` is greater than zero. Default is `Tournament` with size 2.
+    pub fn parent_selection<S: Selection + 'static>(mut self, selection: S) -> PopulationBuilder<T> {
+        self.population.parent_selection = Arc::new(selection);
+        self
+    }
+
+    /// Configures how individuals with equal fitness are sorted: `Random`, `PreferOffspring`,
+    /// `PreferParent` or a `Custom` comparison function. Default is `PreferOffspring`.
+    pub fn tie_break(mut self, tie_break: TieBreak<T>) -> PopulationBuilder<T> {
+        self.population.tie_break = tie_break;
+        self
+    }
+
+    /// Configures the super optimization: if an individual mutates several times per iteration
+    /// the fitness can be calculated after each mutation step and the fittest intermediate state
+    /// is kept (`KeepBest`), or all intermediate states compete in the selection (`KeepAll`).
+    /// This trades additional fitness calculations for quality. Default is `Off`.
+    pub fn super_optimization(mut self, super_optimization: SuperOptimization) -> PopulationBuilder<T> {
+        self.population.super_optimization = super_optimization;
+        self
+    }
+
+    /// Configures how individuals that violate constraints are ranked: by Deb's feasibility
+    /// rules (`FeasibilityFirst`), with a fixed penalty (`Penalty`) or with a penalty that
+    /// adapts to the feasibility of the fittest individual (`AdaptivePenalty`).
+    /// Default is `FeasibilityFirst`.
+    pub fn constraint_handling(mut self, constraint_handling: ConstraintHandling) -> PopulationBuilder<T> {
+        self.population.constraint_handling = constraint_handling;
+        self
+    }
+
+    /// Sets the migration policy of this population, instead of the one of the simulation
+    /// (see `migration_policy` in the `SimulationBuilder`). It decides how many individuals leave
+    /// this population and how they are chosen, and whom the immigrants replace.
+    pub fn migration_policy(mut self, migration_policy: MigrationPolicy) -> PopulationBuilder<T> {
+        self.population.migration_policy = Some(migration_policy);
+        self
+    }
+
     /// Set the population id. Currently this is only used for statistics.
     pub fn set_id(mut self, id: u32) -> PopulationBuilder<T> {
         for individual in &mut self.population.population {
@@ -156,15 +287,57 @@ impl<T: Individual + Clone> PopulationBuilder<T> {
     /// This checks the configuration of the simulation and returns an PopError or Ok if no PopErrors
     /// where found.
     pub fn finalize(self) -> Result<Population<T>> {
+        let num_of_operators = self.population.population.first()
+            .map_or(0, |wrapper| wrapper.operators().len());
+
         match self.population {
-            Population { num_of_individuals: 0...2, ..} => {
+            Population { num_of_individuals: 0..=2, ..} => {
                 Err(ErrorKind::IndividualsTooLow.into())
             }
             Population { reset_limit_start: start,
                          reset_limit_end: end, ..} if (end > 0) && (start >= end) => {
                 Err(ErrorKind::LimitEndTooLow.into())
             }
-            _ => Ok(self.population)
+            Population { crossover_rate: rate, ..} if !(0.0..=1.0).contains(&rate) => {
+                Err(ErrorKind::CrossoverRateInvalid.into())
+            }
+            Population { constraint_handling: ConstraintHandling::Penalty{ weight }, ..} if !(0.0..).contains(&weight) => {
+                Err(ErrorKind::PenaltyInvalid.into())
+            }
+            Population { constraint_handling: ConstraintHandling::AdaptivePenalty{ weight, factor }, ..}
+                if weight.is_nan() || weight <= 0.0 || factor.is_nan() || factor <= 1.0 => {
+                Err(ErrorKind::PenaltyInvalid.into())
+            }
+            Population { migration_policy: Some(MigrationPolicy { num_of_migrants: 0, .. }), ..} => {
+                Err(ErrorKind::MigrationPolicyInvalid.into())
+            }
+            Population { mutation_adaptation: MutationAdaptation::OneFifthRule{ factor, interval, max_mutations }, ..}
+                if factor.is_nan() || factor <= 1.0 || interval == 0 || max_mutations == 0 => {
+                Err(ErrorKind::MutationAdaptationInvalid.into())
+            }
+            Population { mutation_adaptation: MutationAdaptation::SelfAdaptive{ factor, max_mutations }, ..}
+                if factor.is_nan() || factor <= 1.0 || max_mutations == 0 => {
+                Err(ErrorKind::MutationAdaptationInvalid.into())
+            }
+            Population { mutation_adaptation: MutationAdaptation::Decay{ factor }, ..} if !(factor > 0.0 && factor <= 1.0) => {
+                Err(ErrorKind::MutationAdaptationInvalid.into())
+            }
+            Population { ref operator_selection, ..} if !operator_selection.is_valid(num_of_operators) => {
+                Err(ErrorKind::OperatorSelectionInvalid.into())
+            }
+            Population { restart_trigger: RestartTrigger::Stagnation{ iterations: 0 }, ..} => {
+                Err(ErrorKind::RestartInvalid.into())
+            }
+            Population { restart_trigger: RestartTrigger::Diversity{ threshold, .. }, ..} if threshold.is_nan() || threshold < 0.0 => {
+                Err(ErrorKind::RestartInvalid.into())
+            }
+            Population { ref restart_policy, num_of_individuals, ..} if !restart_policy.is_valid(num_of_individuals) => {
+                Err(ErrorKind::RestartInvalid.into())
+            }
+            mut population => {
+                population.restart_state = RestartState::new(population.num_of_individuals);
+                Ok(population)
+            }
         }
     }
 }
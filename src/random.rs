@@ -0,0 +1,94 @@
+//! This module defines `DarwinRng`, the seedable random number generator that is passed to
+//! the individuals, so that a simulation with a seed can be reproduced.
+
+use rand::{self, Rng};
+
+/// The random number generator of a population (xorshift128+). Each population owns one and
+/// passes it to the `mutate`, `crossover` and `reset` methods of its individuals.
+/// It implements the `Rng` trait of the rand crate, so all the usual methods like
+/// `gen_range` are available.
+/// If a seed is given to the `SimulationBuilder`, the generators of all populations are derived
+/// from it and a simulation can be reproduced exactly, regardless of the number of threads.
+/// The state of the generator is saved in a checkpoint.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct DarwinRng {
+    state: [u64; 2],
+}
+
+impl DarwinRng {
+    /// Creates a new generator from the given seed. The same seed always gives the same
+    /// sequence of random numbers.
+    pub fn from_seed(seed: u64) -> DarwinRng {
+        let mut seed = seed;
+        let state = [splitmix64(&mut seed), splitmix64(&mut seed)];
+
+        // The state must not be all zero.
+        if state == [0, 0] {
+            DarwinRng { state: [1, 0] }
+        } else {
+            DarwinRng { state }
+        }
+    }
+
+    /// Creates a new generator with a random seed. This is the default for a population
+    /// if no seed is given.
+    pub fn from_entropy() -> DarwinRng {
+        DarwinRng::from_seed(rand::thread_rng().gen())
+    }
+
+    /// Derives the seed of an independent generator from a master seed and a stream number
+    /// (for example the index of the population).
+    pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+        let mut seed = seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03);
+        splitmix64(&mut seed)
+    }
+}
+
+impl Rng for DarwinRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut s1 = self.state[0];
+        let s0 = self.state[1];
+        let result = s0.wrapping_add(s1);
+
+        self.state[0] = s0;
+        s1 ^= s1 << 23;
+        self.state[1] = s1 ^ s0 ^ (s1 >> 18) ^ (s0 >> 5);
+
+        result
+    }
+}
+
+/// Splitmix64: used to turn a seed into a well mixed state.
+fn splitmix64(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = *seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::DarwinRng;
+
+    #[test]
+    fn from_seed1() {
+        let mut rng1 = DarwinRng::from_seed(42);
+        let mut rng2 = DarwinRng::from_seed(42);
+        let mut rng3 = DarwinRng::from_seed(DarwinRng::derive_seed(42, 1));
+
+        let values1: Vec<u32> = (0..10).map(|_| rng1.gen_range(0, 1000)).collect();
+        let values2: Vec<u32> = (0..10).map(|_| rng2.gen_range(0, 1000)).collect();
+        let values3: Vec<u32> = (0..10).map(|_| rng3.gen_range(0, 1000)).collect();
+
+        assert_eq!(values1, values2);
+        assert!(values1 != values3);
+    }
+}
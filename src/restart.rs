@@ -0,0 +1,184 @@
+//! This module defines when a population is restarted (`RestartTrigger`) and how its size
+//! changes at a restart (`RestartPolicy`, IPOP and BIPOP).
+
+use rand::Rng;
+
+use random::DarwinRng;
+
+/// The `RestartTrigger` type. Specifies when all individuals of a population are reset, since
+/// the population is most likely stuck in a local minimum.
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+pub enum RestartTrigger {
+    /// The fixed schedule given by `reset_limit_start`, `reset_limit_increment` and
+    /// `reset_limit_end`, regardless of the progress of the population. This is the default.
+    #[default]
+    ResetLimit,
+    /// Restart if the fittest individual of the population has not improved for the given number
+    /// of iterations (> 0).
+    Stagnation {
+        /// The number of iterations without improvement.
+        iterations: u32,
+    },
+    /// Restart if the diversity of the population is below `threshold`. The diversity is the mean
+    /// distance of the individuals to the fittest one, see `distance` in the `Individual` trait.
+    Diversity {
+        /// The lowest diversity (>= 0.0) that is accepted.
+        threshold: f64,
+        /// The minimum number of iterations between two restarts, so that a new population
+        /// has some time to spread out.
+        min_iterations: u32,
+    },
+}
+
+/// The `RestartPolicy` type. Specifies the size of the population after a restart.
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+pub enum RestartPolicy {
+    /// The population keeps its size. This is the default.
+    #[default]
+    Reset,
+    /// IPOP: the population grows by `factor` at each restart, up to `max_individuals`.
+    /// A larger population explores more and is less likely to get stuck again.
+    IncreasingPopulation {
+        /// The factor (> 1.0) for the number of individuals.
+        factor: f64,
+        /// The upper limit for the number of individuals.
+        max_individuals: u32,
+    },
+    /// BIPOP: alternates between the large populations of `IncreasingPopulation` and small
+    /// populations with a random size between the initial and the current large size. The regime
+    /// that has used fewer fitness evaluations so far is chosen, so both get the same budget.
+    BiPopulation {
+        /// The factor (> 1.0) for the number of individuals of the large populations.
+        factor: f64,
+        /// The upper limit for the number of individuals.
+        max_individuals: u32,
+    },
+}
+
+/// The `RestartState` type. The book keeping of the restarts of a population.
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+pub struct RestartState {
+    /// The number of iterations since the fittest individual has improved.
+    pub stagnation_counter: u32,
+    /// The number of iterations since the last restart.
+    pub iterations_since_restart: u32,
+    /// The number of individuals the population has been created with.
+    pub initial_num_of_individuals: u32,
+    /// The current size of the large populations (`BiPopulation`).
+    pub large_num_of_individuals: u32,
+    /// Is the current population a large one (`BiPopulation`) ?
+    pub large_regime: bool,
+    /// The number of fitness evaluations of the population at the last restart.
+    pub evaluations_at_restart: u64,
+    /// The number of fitness evaluations used by the large populations (`BiPopulation`).
+    pub large_evaluations: u64,
+    /// The number of fitness evaluations used by the small populations (`BiPopulation`).
+    pub small_evaluations: u64,
+}
+
+impl RestartState {
+    /// Creates the state for a new population with the given number of individuals.
+    pub fn new(num_of_individuals: u32) -> RestartState {
+        RestartState {
+            initial_num_of_individuals: num_of_individuals,
+            large_num_of_individuals: num_of_individuals,
+            large_regime: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Returns true if the parameters are valid for a population with the given number of
+    /// individuals.
+    pub fn is_valid(&self, num_of_individuals: u32) -> bool {
+        match *self {
+            RestartPolicy::Reset => true,
+            RestartPolicy::IncreasingPopulation{ factor, max_individuals } |
+            RestartPolicy::BiPopulation{ factor, max_individuals } =>
+                factor > 1.0 && max_individuals >= num_of_individuals,
+        }
+    }
+
+    /// Returns the number of individuals after a restart. `num_of_evaluations` is the current
+    /// number of fitness evaluations of the population, it is used to balance the budgets of
+    /// `BiPopulation`.
+    pub fn next_num_of_individuals(&self, state: &mut RestartState, num_of_individuals: u32,
+        num_of_evaluations: u64, rng: &mut DarwinRng) -> u32 {
+        let grow = |size: u32, factor: f64, max_individuals: u32|
+            ((size as f64 * factor).ceil() as u32).min(max_individuals);
+
+        match *self {
+            RestartPolicy::Reset => num_of_individuals,
+            RestartPolicy::IncreasingPopulation{ factor, max_individuals } =>
+                grow(num_of_individuals, factor, max_individuals),
+            RestartPolicy::BiPopulation{ factor, max_individuals } => {
+                let used = num_of_evaluations - state.evaluations_at_restart;
+
+                if state.large_regime {
+                    state.large_evaluations += used;
+                } else {
+                    state.small_evaluations += used;
+                }
+
+                state.evaluations_at_restart = num_of_evaluations;
+                state.large_regime = state.small_evaluations >= state.large_evaluations;
+
+                if state.large_regime {
+                    state.large_num_of_individuals = grow(state.large_num_of_individuals, factor, max_individuals);
+                    state.large_num_of_individuals
+                } else {
+                    // The size is distributed log-uniform squared, so small populations are
+                    // more likely.
+                    let initial = state.initial_num_of_individuals as f64;
+                    let ratio = state.large_num_of_individuals as f64 / initial;
+                    (initial * ratio.powf(rng.gen::<f64>().powi(2))).floor() as u32
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use random::DarwinRng;
+
+    use super::{RestartPolicy, RestartState};
+
+    #[test]
+    fn next_num_of_individuals1() {
+        let mut rng = DarwinRng::from_seed(1);
+        let mut state = RestartState::new(10);
+
+        let policy = RestartPolicy::IncreasingPopulation{ factor: 2.0, max_individuals: 30 };
+        assert_eq!(policy.next_num_of_individuals(&mut state, 10, 0, &mut rng), 20);
+        assert_eq!(policy.next_num_of_individuals(&mut state, 20, 0, &mut rng), 30);
+        assert!(!policy.is_valid(40));
+
+        // The large population has used 100 evaluations, so a small one follows. There has not
+        // been a larger population yet, so it has the initial size.
+        let policy = RestartPolicy::BiPopulation{ factor: 2.0, max_individuals: 100 };
+        let size = policy.next_num_of_individuals(&mut state, 10, 100, &mut rng);
+        assert!(!state.large_regime);
+        assert_eq!(size, 10);
+
+        // The small population has used 150 evaluations, now it is the turn of a large one.
+        assert_eq!(policy.next_num_of_individuals(&mut state, size, 250, &mut rng), 20);
+        assert!(state.large_regime);
+        assert_eq!(state.large_evaluations, 100);
+        assert_eq!(state.small_evaluations, 150);
+
+        // The large population has used 200 evaluations, so a small one follows. Its size is
+        // drawn between the initial and the current large size.
+        let sizes: Vec<u32> = (0..20).map(|_| {
+            let mut state = state.clone();
+            let size = policy.next_num_of_individuals(&mut state, 20, 450, &mut rng);
+            assert!(!state.large_regime);
+            assert_eq!(state.large_num_of_individuals, 20);
+            size
+        }).collect();
+
+        assert!(sizes.iter().all(|size| (10..20).contains(size)));
+        assert!(sizes.iter().any(|size| *size > 10));
+    }
+}
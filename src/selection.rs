@@ -0,0 +1,308 @@
+//! This module defines the selection strategies that are used to choose the surviving individuals
+//! of a population and the partners for a crossover: the `Selection` trait and the built-in
+//! strategies from truncation to stochastic universal sampling.
+
+use std::sync::Arc;
+
+use rand::Rng;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use random::DarwinRng;
+
+/// This trait has to be implemented for a selection strategy.
+/// Use the `selection` and `parent_selection` methods of the `PopulationBuilder` to set it.
+/// The built-in strategies are `Truncation`, `Tournament`, `RouletteWheel`, `RankBased` and
+/// `StochasticUniversalSampling`.
+pub trait Selection: Send + Sync {
+    /// This method selects `num_of_selected` individuals out of the given candidates.
+    /// The fitness values of the candidates are sorted, the fittest candidate comes first
+    /// (index 0). It returns the indices of the selected candidates sorted in ascending order.
+    /// The same index may be returned several times, the candidate will then be copied.
+    /// If there are no candidates, no index is returned.
+    /// Use the given random number generator to keep the simulation reproducible.
+    fn select(&self, fitness: &[f64], num_of_selected: usize, rng: &mut DarwinRng) -> Vec<usize>;
+
+    /// Returns the description of a built-in strategy, so that it can be saved in a checkpoint.
+    /// User defined strategies return `None` (default), they have to be set again with
+    /// `restore_selection` or `restore_parent_selection` in the `SimulationBuilder` after the
+    /// simulation has been restored.
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        None
+    }
+}
+
+/// The `BuiltInSelection` type. Describes one of the built-in selection strategies, this is what
+/// is saved in a checkpoint.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum BuiltInSelection {
+    /// See `Truncation`.
+    Truncation,
+    /// See `Tournament`.
+    Tournament {
+        /// The number of candidates competing in each tournament.
+        size: usize,
+    },
+    /// See `RouletteWheel`.
+    RouletteWheel,
+    /// See `RankBased`.
+    RankBased {
+        /// The expected number of copies of the fittest candidate, between 1.0 and 2.0.
+        selection_pressure: f64,
+    },
+    /// See `StochasticUniversalSampling`.
+    StochasticUniversalSampling,
+}
+
+impl BuiltInSelection {
+    /// Creates the selection strategy.
+    pub fn to_selection(&self) -> Arc<dyn Selection> {
+        match *self {
+            BuiltInSelection::Truncation => Arc::new(Truncation),
+            BuiltInSelection::Tournament{ size } => Arc::new(Tournament{ size }),
+            BuiltInSelection::RouletteWheel => Arc::new(RouletteWheel),
+            BuiltInSelection::RankBased{ selection_pressure } => Arc::new(RankBased{ selection_pressure }),
+            BuiltInSelection::StochasticUniversalSampling => Arc::new(StochasticUniversalSampling),
+        }
+    }
+}
+
+/// Stands in for a user defined strategy that has not been restored from a checkpoint yet.
+/// The `SimulationBuilder` does not accept a simulation that still contains it.
+pub(crate) struct Unrestored;
+
+impl Selection for Unrestored {
+    fn select(&self, _fitness: &[f64], _num_of_selected: usize, _rng: &mut DarwinRng) -> Vec<usize> {
+        panic!("the user defined selection strategy has not been restored from the checkpoint");
+    }
+}
+
+/// Writes a selection strategy to a checkpoint (use with `#[serde(with = "...")]`).
+/// A user defined strategy is written as `null` and restored as `Unrestored`.
+pub(crate) mod checkpoint_selection {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(selection: &Arc<dyn Selection>, serializer: S) -> Result<S::Ok, S::Error> {
+        selection.built_in().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<dyn Selection>, D::Error> {
+        Ok(Option::<BuiltInSelection>::deserialize(deserializer)?
+            .map_or_else(|| Arc::new(Unrestored) as Arc<dyn Selection>, |selection| selection.to_selection()))
+    }
+}
+
+/// Truncation selection: just keep the `num_of_selected` fittest candidates.
+/// This is the default strategy for survivor selection. It has the highest selection pressure.
+#[derive(Debug,Clone)]
+pub struct Truncation;
+
+impl Selection for Truncation {
+    fn select(&self, fitness: &[f64], num_of_selected: usize, _rng: &mut DarwinRng) -> Vec<usize> {
+        (0..num_of_selected.min(fitness.len())).collect()
+    }
+
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        Some(BuiltInSelection::Truncation)
+    }
+}
+
+/// Tournament selection: pick `size` candidates at random, the fittest of them wins.
+/// This is repeated until enough candidates are selected. Larger tournaments mean a higher
+/// selection pressure. This is the default strategy for parent selection (with size 2).
+#[derive(Debug,Clone)]
+pub struct Tournament {
+    /// The number of candidates competing in each tournament.
+    pub size: usize,
+}
+
+impl Selection for Tournament {
+    fn select(&self, fitness: &[f64], num_of_selected: usize, rng: &mut DarwinRng) -> Vec<usize> {
+        if fitness.is_empty() {
+            return Vec::new();
+        }
+
+        let mut result: Vec<usize> = (0..num_of_selected).map(|_| {
+            // Candidates are sorted, so the lowest index is the fittest one.
+            (0..self.size.max(1)).map(|_| rng.gen_range(0, fitness.len())).min().unwrap()
+        }).collect();
+
+        result.sort();
+        result
+    }
+
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        Some(BuiltInSelection::Tournament{ size: self.size })
+    }
+}
+
+/// Roulette wheel selection (fitness proportional): the chance of a candidate to be selected is
+/// proportional to the distance of its fitness from the least fit candidate.
+#[derive(Debug,Clone)]
+pub struct RouletteWheel;
+
+impl Selection for RouletteWheel {
+    fn select(&self, fitness: &[f64], num_of_selected: usize, rng: &mut DarwinRng) -> Vec<usize> {
+        if fitness.is_empty() {
+            return Vec::new();
+        }
+
+        let weights = fitness_weights(fitness);
+        let total: f64 = weights.iter().sum();
+
+        let mut result: Vec<usize> = (0..num_of_selected)
+            .map(|_| spin(&weights, rng.gen::<f64>() * total)).collect();
+
+        result.sort();
+        result
+    }
+
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        Some(BuiltInSelection::RouletteWheel)
+    }
+}
+
+/// Rank based selection (linear ranking): the chance of a candidate to be selected only depends
+/// on its position in the sorted candidates, not on the actual fitness value.
+/// The selection pressure must be between 1.0 (all candidates have the same chance) and
+/// 2.0 (the least fit candidate has no chance at all).
+#[derive(Debug,Clone)]
+pub struct RankBased {
+    /// The expected number of copies of the fittest candidate, between 1.0 and 2.0.
+    pub selection_pressure: f64,
+}
+
+impl Selection for RankBased {
+    fn select(&self, fitness: &[f64], num_of_selected: usize, rng: &mut DarwinRng) -> Vec<usize> {
+        if fitness.is_empty() {
+            return Vec::new();
+        }
+
+        let pressure = self.selection_pressure.clamp(1.0, 2.0);
+        let last = (fitness.len().max(2) - 1) as f64;
+
+        let weights: Vec<f64> = (0..fitness.len())
+            .map(|rank| pressure - (2.0 * pressure - 2.0) * (rank as f64) / last).collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut result: Vec<usize> = (0..num_of_selected)
+            .map(|_| spin(&weights, rng.gen::<f64>() * total)).collect();
+
+        result.sort();
+        result
+    }
+
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        Some(BuiltInSelection::RankBased{ selection_pressure: self.selection_pressure })
+    }
+}
+
+/// Stochastic universal sampling: like the roulette wheel selection but with evenly spaced
+/// pointers and only one spin. This has less bias than spinning the roulette wheel several times.
+#[derive(Debug,Clone)]
+pub struct StochasticUniversalSampling;
+
+impl Selection for StochasticUniversalSampling {
+    fn select(&self, fitness: &[f64], num_of_selected: usize, rng: &mut DarwinRng) -> Vec<usize> {
+        let weights = fitness_weights(fitness);
+        let total: f64 = weights.iter().sum();
+
+        if fitness.is_empty() || num_of_selected == 0 {
+            return Vec::new();
+        }
+
+        let distance = total / (num_of_selected as f64);
+        let start = rng.gen::<f64>() * distance;
+
+        // The pointers are increasing, so the result is already sorted.
+        (0..num_of_selected)
+            .map(|i| spin(&weights, start + (i as f64) * distance)).collect()
+    }
+
+    fn built_in(&self) -> Option<BuiltInSelection> {
+        Some(BuiltInSelection::StochasticUniversalSampling)
+    }
+}
+
+/// Calculate the weights for a fitness proportional selection. The fitness values are sorted,
+/// so the last value is the least fit one. If all values are equal, every candidate gets the
+/// same weight.
+fn fitness_weights(fitness: &[f64]) -> Vec<f64> {
+    let worst = fitness.last().cloned().unwrap_or(0.0);
+    let weights: Vec<f64> = fitness.iter().map(|value| (value - worst).abs()).collect();
+
+    if weights.iter().sum::<f64>() > 0.0 {
+        weights
+    } else {
+        vec![1.0; fitness.len()]
+    }
+}
+
+/// Return the index of the slot of the roulette wheel that the pointer points to.
+fn spin(weights: &[f64], pointer: f64) -> usize {
+    let mut sum = 0.0;
+
+    for (index, weight) in weights.iter().enumerate() {
+        sum += *weight;
+        if pointer < sum {
+            return index;
+        }
+    }
+
+    // Rounding errors, pointer is at the very end of the wheel.
+    weights.len() - 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Selection, Truncation, Tournament, RouletteWheel, RankBased,
+        StochasticUniversalSampling};
+    use random::DarwinRng;
+
+    #[test]
+    fn truncation() {
+        assert_eq!(Truncation.select(&[1.0, 2.0, 3.0, 4.0], 2, &mut DarwinRng::from_seed(1)), vec![0, 1]);
+    }
+
+    #[test]
+    fn tournament() {
+        let selected = Tournament{ size: 2 }.select(&[1.0, 2.0, 3.0, 4.0], 10, &mut DarwinRng::from_seed(1));
+
+        assert_eq!(selected.len(), 10);
+        assert!(selected.iter().all(|index| *index < 4));
+    }
+
+    #[test]
+    fn roulette_wheel() {
+        // The least fit candidate has weight zero and is never selected.
+        let selected = RouletteWheel.select(&[1.0, 2.0, 3.0, 4.0], 20, &mut DarwinRng::from_seed(1));
+
+        assert_eq!(selected.len(), 20);
+        assert!(selected.iter().all(|index| *index < 3));
+    }
+
+    #[test]
+    fn rank_based() {
+        let selected = RankBased{ selection_pressure: 2.0 }.select(&[1.0, 1.0, 1.0], 20, &mut DarwinRng::from_seed(1));
+
+        assert_eq!(selected.len(), 20);
+        assert!(selected.iter().all(|index| *index < 2));
+    }
+
+    #[test]
+    fn empty() {
+        let strategies: [&dyn Selection; 5] = [&Truncation, &Tournament{ size: 2 }, &RouletteWheel,
+            &RankBased{ selection_pressure: 1.5 }, &StochasticUniversalSampling];
+
+        for strategy in &strategies {
+            assert!(strategy.select(&[], 3, &mut DarwinRng::from_seed(1)).is_empty());
+        }
+    }
+
+    #[test]
+    fn stochastic_universal_sampling() {
+        // Only the first two candidates have a weight (3.0 and 1.0).
+        let selected = StochasticUniversalSampling.select(&[1.0, 3.0, 4.0, 4.0], 4, &mut DarwinRng::from_seed(1));
+
+        assert_eq!(selected, vec![0, 0, 0, 1]);
+    }
+}
@@ -13,36 +13,272 @@
 //!
 //!
 
-use std::time::Instant;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use jobsteal::make_pool;
+use rand::Rng;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::DeserializeOwned;
+use serde_json;
 
+use executor::{Executor, Job, JobstealPool};
+use fitness::Fitness;
 use individual::{Individual, IndividualWrapper};
+use island::Island;
+use migration::{MigrationTopology, MigrationEdge, MigrationPolicy};
+use observer::Observer;
+use pareto;
 use population::Population;
+use random::DarwinRng;
+use simulation_handle::SimulationHandle;
+use statistics::{self, IterationStatistics};
+
+error_chain! {
+    errors {
+        CheckpointWrite(reason: String) {
+            description("could not write checkpoint")
+            display("could not write checkpoint: {}", reason)
+        }
+        CheckpointRead(reason: String) {
+            description("could not read checkpoint")
+            display("could not read checkpoint: {}", reason)
+        }
+        HistoryWrite(reason: String) {
+            description("could not write history")
+            display("could not write history: {}", reason)
+        }
+        AsynchronousSpawn {
+            description("an asynchronous simulation can not be spawned, use run instead")
+            display("an asynchronous simulation can not be spawned, use run instead")
+        }
+    }
+}
+
+/// The `Direction` type. Specifies if a lower or a higher fitness is better.
+/// This is honoured by sorting the populations, the stop criteria, the global fittest
+/// individuals and the improvement factor.
+#[derive(Debug,Clone,Copy,PartialEq,Serialize,Deserialize)]
+pub enum Direction {
+    /// The lower the fitness, the better. This is the default.
+    Minimize,
+    /// The higher the fitness, the better.
+    Maximize,
+}
+
+impl Direction {
+    /// Compares two fitness values, the better one comes first (`Ordering::Less`).
+    /// A NaN fitness is always the worst one, regardless of the direction.
+    pub fn compare<F: Fitness>(&self, fitness1: &F, fitness2: &F) -> Ordering {
+        match (fitness1.is_nan(), fitness2.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ordering = fitness1.partial_cmp(fitness2).unwrap_or(Ordering::Equal);
+
+                match *self {
+                    Direction::Minimize => ordering,
+                    Direction::Maximize => ordering.reverse(),
+                }
+            }
+        }
+    }
+
+    /// Returns true if `fitness1` is better than `fitness2`.
+    pub fn is_better<F: Fitness>(&self, fitness1: &F, fitness2: &F) -> bool {
+        self.compare(fitness1, fitness2) == Ordering::Less
+    }
+}
+
+/// The `Scheduling` type. Specifies how the work of one iteration is distributed over the
+/// threads of the simulation. The result is the same in all cases, if a seed is given.
+/// An `asynchronous` simulation always runs one thread per population.
+#[derive(Debug,Clone,Copy,PartialEq,Serialize,Deserialize)]
+pub enum Scheduling {
+    /// Each population is one job for the executor. This is best if there are at least as
+    /// many populations as threads.
+    PerPopulation,
+    /// The individuals of each population are split into one chunk per thread and each chunk
+    /// is one job for the executor, so even a single population can use all threads.
+    /// This is best if the fitness calculation is expensive.
+    PerIndividual,
+    /// `PerIndividual` if there are fewer populations than threads of the executor, otherwise
+    /// `PerPopulation`.
+    /// This is the default.
+    Automatic,
+}
 
 /// The `SimulationType` type. Speficies the criteria on how a simulation should stop.
-#[derive(Debug,Clone)]
-pub enum SimulationType {
+/// It is generic over the fitness type of the individuals.
+/// The criteria can be combined with `AnyOf` and `AllOf`, for example
+/// `AnyOf(vec![EndFitness(0.0), EndTime(Duration::from_secs(60))])` stops when the fitness
+/// is reached, but after one minute at the latest.
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub enum SimulationType<F> {
     /// Finish the simulation when a number of iteration has been reached.
     EndIteration(u32),
     /// Finish the simulation when a specific fitness is rached.
-    /// That means if at least one of the individuals has this fitness.
+    /// That means if at least one of the individuals has this (or a better) fitness.
     /// The fitness is calculated using the implemented `calculate_fitness` functions
-    /// of the `Individual` trait.
-    EndFitness(f64),
+    /// of the `Individual` trait. The fittest individual must also be feasible.
+    EndFitness(F),
     /// Finish the simulation when a specific improvement factor is reached.
-    /// That means the relation between the very first fitness and the current fitness of the
-    /// fittest individual.
+    /// That means the relation between the current fitness of the fittest individual and the
+    /// very first fitness. When minimizing the factor has to be lower or equal, when maximizing
+    /// the factor has to be higher or equal. The fittest individual must also be feasible.
     EndFactor(f64),
+    /// Finish the simulation when the total run time has reached this duration.
+    /// The current iteration is always completed.
+    EndTime(Duration),
+    /// Finish the simulation when the total number of fitness evaluations of all populations
+    /// has reached this value.
+    EndEvaluations(u64),
+    /// Finish the simulation when no new fittest individual has been found in this number
+    /// of iterations.
+    EndStagnation(u32),
+    /// Finish the simulation when at least one of the criteria is reached.
+    AnyOf(Vec<SimulationType<F>>),
+    /// Finish the simulation when all of the criteria are reached.
+    AllOf(Vec<SimulationType<F>>),
+    /// Finish the simulation when the user defined function returns true.
+    /// See `SimulationType::custom`.
+    Custom(CustomCriterion<F>),
+}
+
+impl<F> SimulationType<F> {
+    /// Creates a user defined stop criterion. The function gets the current progress of the
+    /// simulation after each iteration and returns true if the simulation should stop.
+    pub fn custom<C>(criterion: C) -> SimulationType<F>
+        where C: Fn(&SimulationProgress<F>) -> bool + Send + Sync + 'static {
+        SimulationType::Custom(CustomCriterion(Arc::new(criterion)))
+    }
+
+    /// Returns true if this criterion is or contains a user defined one, which can not be
+    /// restored from a checkpoint.
+    pub(crate) fn is_custom(&self) -> bool {
+        match *self {
+            SimulationType::AnyOf(ref criteria) | SimulationType::AllOf(ref criteria) =>
+                criteria.iter().any(|criterion| criterion.is_custom()),
+            SimulationType::Custom(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A user defined stop criterion, see `SimulationType::custom`.
+/// The function can not be saved in a checkpoint, it has to be set again with `termination` in
+/// the `SimulationBuilder` after the simulation has been restored.
+pub struct CustomCriterion<F>(pub Arc<CriterionFn<F>>);
+
+/// The function of a `CustomCriterion`.
+pub type CriterionFn<F> = dyn Fn(&SimulationProgress<F>) -> bool + Send + Sync;
+
+impl<F> Clone for CustomCriterion<F> {
+    fn clone(&self) -> CustomCriterion<F> {
+        CustomCriterion(self.0.clone())
+    }
+}
+
+impl<F> fmt::Debug for CustomCriterion<F> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "CustomCriterion")
+    }
+}
+
+impl<F> Serialize for CustomCriterion<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de, F> Deserialize<'de> for CustomCriterion<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<CustomCriterion<F>, D::Error> {
+        <()>::deserialize(deserializer)?;
+        Ok(CustomCriterion(Arc::new(|_| panic!("the user defined stop criterion has not been restored from the checkpoint"))))
+    }
+}
+
+/// The `SimulationProgress` type. A snapshot of the progress of a simulation, returned by the
+/// `step` and `step_n` methods of the `Simulation`.
+#[derive(Debug,Clone)]
+pub struct SimulationProgress<F> {
+    /// The number of iterations so far.
+    pub iteration: u32,
+    /// The fitness of the fittest individual so far.
+    pub fitness: F,
+    /// The current improvement factor.
+    pub improvement_factor: f64,
+    /// The total number of failed evaluations.
+    pub num_of_failures: u64,
+    /// The total number of fitness evaluations.
+    pub num_of_evaluations: u64,
+    /// The iteration in which the last new fittest individual has been found.
+    pub last_improvement: u32,
+    /// The total run time of the simulation so far.
+    pub total_time_in_ms: f64,
+    /// True if the stop condition of the simulation is reached.
+    pub finished: bool,
+}
+
+/// The `Checkpoint` type. Specifies where and how often the simulation is saved automatically
+/// while it is running. Use `checkpoint` in the `SimulationBuilder` to set it.
+#[derive(Clone)]
+pub struct Checkpoint<T: Individual + Send + Sync> {
+    /// The file the simulation is saved to.
+    pub path: PathBuf,
+    /// Save the simulation every nth iteration.
+    pub every: u32,
+    /// The function that actually saves the simulation.
+    pub save: fn(&Simulation<T>, &Path) -> Result<()>,
+}
+
+/// No automatic checkpoint, used when a simulation is restored.
+fn no_checkpoint<T: Individual + Send + Sync>() -> Option<Checkpoint<T>> {
+    None
+}
+
+/// No observers, used when a simulation is restored.
+fn no_observers<T: Individual + Send + Sync>() -> Vec<Box<dyn Observer<T>>> {
+    Vec::new()
 }
 
 /// The `Simulation` type. Contains all the information / configuration for the simulation to run.
 /// Use the `SimulationBuilder` in order to create a simulation.
+/// The whole simulation can be saved to a checkpoint file with `save_checkpoint` and restored
+/// with `from_checkpoint` in the `SimulationBuilder`, if the individuals implement `Serialize`
+/// and `Deserialize`.
+#[derive(Serialize,Deserialize)]
+#[serde(bound(serialize = "T: ::serde::Serialize, T::Fitness: ::serde::Serialize",
+    deserialize = "T: ::serde::de::DeserializeOwned, T::Fitness: ::serde::de::DeserializeOwned"))]
 pub struct Simulation<T: Individual + Send + Sync> {
     /// How should the simulation stop ?
-    pub type_of_simulation: SimulationType,
+    pub type_of_simulation: SimulationType<T::Fitness>,
+    /// Is a lower or a higher fitness better ? Default: `Minimize`.
+    pub direction: Direction,
+    /// If this feature is enabled, the individuals are sorted by the Pareto rank and crowding
+    /// distance of their objectives (NSGA-II), instead of their fitness.
+    /// See the `objectives` method of the `Individual` trait.
+    pub multi_objective: bool,
+    /// The maximum number of individuals in the Pareto front of the simulation result, default: 100
+    /// If there are more non-dominated individuals, the ones in the most crowded regions are
+    /// discarded.
+    pub pareto_front_size: usize,
+    /// The master seed for the random number generators of the populations, see `seed` in the
+    /// `SimulationBuilder`. `None` means random seeds.
+    pub seed: Option<u64>,
     /// The number of threads to use to speed up calculation.
     pub num_of_threads: usize,
+    /// How the populations or individuals are distributed over the threads, default: `Automatic`
+    pub scheduling: Scheduling,
     /// All the populations for the simulation. Contains all individuals for the simulation.
     pub habitat: Vec<Population<T>>,
     /// The total run time for the simulation. This will be calculated once the stimulation has
@@ -51,9 +287,19 @@ pub struct Simulation<T: Individual + Send + Sync> {
     /// The result of the simulation: `improvement_factor`, `original_fitness` and a vector of
     /// fittest individuals.
     pub simulation_result: SimulationResult<T>,
-    /// If this feature is enabled, then the most fittest individual of all populations is
-    /// shared between all the populations.
+    /// If this feature is enabled, then individuals migrate between the populations
+    /// according to the `migration_topology`.
     pub share_fittest: bool,
+    /// Which populations exchange individuals, default: `Global`, the most fittest individual
+    /// of all populations is shared between all the populations.
+    pub migration_topology: MigrationTopology,
+    /// The edges of the migration topology, they are created by the `SimulationBuilder`.
+    pub migration_edges: Vec<MigrationEdge>,
+    /// How many individuals migrate, how they are chosen and whom they replace. This can be
+    /// changed for each population, see `migration_policy` in the `PopulationBuilder`.
+    pub migration_policy: MigrationPolicy,
+    /// The random number generator for the migration. It is derived from the seed, if given.
+    pub migration_rng: DarwinRng,
     /// The total number of global fittest individual to keep, default: 10
     /// After each interation the most fittest individual of all populations is determinded.
     /// And this individual is copied into a global "high score list" of the whole simulation,
@@ -68,37 +314,268 @@ pub struct Simulation<T: Individual + Send + Sync> {
     /// the new fittest individual will be written to the log.
     pub output_every_counter: u32,
     /// Only share the most fittest individual between the populations if the counter reaches
-    /// this value: share_counter >= share_every.
+    /// this value: share_counter >= share_every. For the other migration topologies this is the
+    /// migration interval of all edges.
     pub share_every: u32,
     /// Counter that will be incremented every iteration. If share_counter >= share_every then the
-    /// most fittest individual is shared between all the populations.
-    pub share_counter: u32
+    /// most fittest individual is shared between all the populations (`Global` topology only).
+    pub share_counter: u32,
+    /// Save the simulation automatically to a checkpoint file while it is running.
+    /// This is not saved in the checkpoint itself.
+    #[serde(skip, default = "no_checkpoint")]
+    pub checkpoint: Option<Checkpoint<T>>,
+    /// Record the statistics of each population after each iteration (or epoch) in the `history`
+    /// of the simulation result. Default: off
+    pub record_history: bool,
+    /// The number of iterations each population runs in one epoch, before the results are
+    /// updated and the individuals migrate. Default: 1
+    pub iterations_per_epoch: u32,
+    /// Run each population on its own thread without waiting for the other populations after
+    /// each iteration, see `asynchronous` in the `SimulationBuilder`. Default: off
+    pub asynchronous: bool,
+    /// The observers that get notified about the events of the simulation.
+    /// These are not saved in the checkpoint.
+    #[serde(skip, default = "no_observers")]
+    pub observers: Vec<Box<dyn Observer<T>>>,
+    /// The executor that runs the populations, see `executor` in the `SimulationBuilder`.
+    /// If none is given, a `JobstealPool` with `num_of_threads` threads is created by the first
+    /// iteration. This is not saved in the checkpoint.
+    #[serde(skip)]
+    pub executor: Option<Arc<dyn Executor>>,
 }
 
 /// The `SimulationResult` Type. Holds the simulation results:
 /// All the fittest individuals, the `improvement_factor`, the `iteration_counter` and the
 /// `original_fitness`.
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
+#[serde(bound(serialize = "T: ::serde::Serialize, T::Fitness: ::serde::Serialize",
+    deserialize = "T: ::serde::de::DeserializeOwned, T::Fitness: ::serde::de::DeserializeOwned"))]
 pub struct SimulationResult<T: Individual + Send + Sync> {
-    /// The current improvement factor, that means the ration between the very first and the
-    /// current fitness.
+    /// The current improvement factor, that means the ratio between the current and the very
+    /// first fitness. When minimizing it decreases, when maximizing it increases.
+    #[serde(with = "::fitness::checkpoint_f64")]
     pub improvement_factor: f64,
     /// The very first calculated fitness, when the simulation just started.
-    pub original_fitness: f64,
+    #[serde(with = "::fitness::checkpoint_fitness")]
+    pub original_fitness: T::Fitness,
     /// Vector of fittest individuals. This will change during the simulation as soon as a new
     /// more fittest individual is found and pushed into the first position (index 0).
     pub fittest: Vec<IndividualWrapper<T>>,
-    /// How many iteration did the simulation run.
-    pub iteration_counter: u32
+    /// The non-dominated individuals found so far in a multi-objective optimization
+    /// (Pareto front). Empty if `multi_objective` is not enabled.
+    pub pareto_front: Vec<IndividualWrapper<T>>,
+    /// How many iteration did the simulation run. A simulation that is restored from a
+    /// checkpoint continues to count from this value.
+    pub iteration_counter: u32,
+    /// The total number of failed evaluations of all populations: individuals that panicked
+    /// or had a NaN fitness. See `num_of_failures` in the `Population`.
+    pub num_of_failures: u64,
+    /// The total number of fitness evaluations of all populations.
+    pub num_of_evaluations: u64,
+    /// The iteration in which the last new fittest individual has been found.
+    pub last_improvement: u32,
+    /// The statistics of each population after each iteration (or epoch, if `iterations_per_epoch`
+    /// is set), if `record_history` is enabled in the `SimulationBuilder`. Otherwise empty.
+    #[serde(default)]
+    pub history: Vec<IterationStatistics>,
 }
 
-/// This implements the the functions `run`, `print_fitness` and `update_results` (private)
-/// for the struct `Simulation`.
+impl<T: Individual + Send + Sync> SimulationResult<T> {
+    /// Save the recorded history to the given file as CSV, one line per iteration and population.
+    pub fn save_history_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path).map_err(|e| ErrorKind::HistoryWrite(e.to_string()))?;
+        statistics::write_csv(&self.history, BufWriter::new(file))
+            .map_err(|e| ErrorKind::HistoryWrite(e.to_string()).into())
+    }
+
+    /// Save the recorded history to the given file as JSON Lines, one JSON object per
+    /// iteration and population.
+    pub fn save_history_json_lines<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path).map_err(|e| ErrorKind::HistoryWrite(e.to_string()))?;
+        statistics::write_json_lines(&self.history, BufWriter::new(file))
+            .map_err(|e| ErrorKind::HistoryWrite(e.to_string()).into())
+    }
+}
+
+/// This implements the the functions `run`, `init`, `step`, `print_fitness` and `update_results`
+/// (private) for the struct `Simulation`.
 impl<T: Individual + Send + Sync + Clone> Simulation<T> {
     /// This actually runs the simulation.
     /// Depending on the type of simulation (`EndIteration`, `EndFactor` or `EndFitness`)
     /// the iteration loop will check for the stop condition accordingly.
+    /// If the simulation has already been running (for example it has been restored from a
+    /// checkpoint), it just continues.
+    /// This is the same as calling `init` and then `step` until the simulation is finished.
+    /// At the end the statistics of the mutation operators are written to the log.
     pub fn run(&mut self) {
+        self.init();
+
+        if self.asynchronous {
+            self.run_asynchronous();
+        } else {
+            while !self.is_finished() {
+                self.step();
+            }
+        }
+
+        for population in &self.habitat {
+            for operator in &population.operator_statistics {
+                info!("operator: {}, population: {}, applications: {}, successes: {}, quality: {}, probability: {}",
+                    operator.name, population.id, operator.num_of_applications, operator.num_of_successes,
+                    operator.quality, operator.probability);
+            }
+        }
+
+        self.save_automatic_checkpoint(true);
+    }
+
+    /// Runs each population on its own thread until the simulation is finished. The populations
+    /// do not wait for each other, the migrants and the results are exchanged through channels.
+    /// Whenever each population has reported one iteration on average, the iteration counter
+    /// is incremented and the stop condition is checked. The iterations that the populations
+    /// finish after the stop condition has been reached are counted as well, so that the results
+    /// match the populations.
+    fn run_asynchronous(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+
+        let start_time = Instant::now();
+        let num_of_populations = self.habitat.len();
+        let population_ids: Vec<u32> = self.habitat.iter().map(|population| population.id).collect();
+        let mut fitness_counters: Vec<u64> = self.habitat.iter().map(|population| population.fitness_counter).collect();
+        let mut num_of_failures: Vec<u64> = self.habitat.iter().map(|population| population.num_of_failures).collect();
+        let mut num_of_evaluations: Vec<u64> = self.habitat.iter().map(|population| population.num_of_evaluations).collect();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let fittest = Arc::new(Mutex::new(self.simulation_result.fittest[0].clone()));
+        let (report_sender, report_receiver) = mpsc::channel();
+        let mut immigrant_senders = Vec::new();
+        let mut islands = Vec::new();
+
+        for (index, population) in mem::take(&mut self.habitat).into_iter().enumerate() {
+            let (immigrant_sender, immigrant_receiver) = mpsc::channel();
+            immigrant_senders.push(immigrant_sender);
+
+            islands.push(Island {
+                index,
+                population,
+                iteration: self.simulation_result.iteration_counter,
+                immigrants: immigrant_receiver,
+                reports: report_sender.clone(),
+                stop: stop.clone(),
+                fittest: fittest.clone(),
+                edges: self.migration_edges.iter().filter(|edge| edge.source == index).cloned().collect(),
+                migration_policy: self.migration_policy.clone(),
+                rng: DarwinRng::from_seed(self.migration_rng.gen()),
+                direction: self.direction,
+                record_history: self.record_history,
+                start_time,
+                start_time_in_ms: self.total_time_in_ms,
+            });
+        }
+
+        // The reports end when all islands have stopped.
+        drop(report_sender);
+
+        let start_time_in_ms = self.total_time_in_ms;
+        let share_global = self.share_fittest && self.migration_topology == MigrationTopology::Global;
+        let mut num_of_reports = 0;
+        let mut new_fittest_found = None;
+
+        self.habitat = thread::scope(|scope| {
+            let handles: Vec<_> = islands.into_iter()
+                .map(|island| scope.spawn(move || island.run())).collect();
+
+            for report in report_receiver {
+                let index = report.index;
+                num_of_failures[index] = report.num_of_failures;
+                num_of_evaluations[index] = report.num_of_evaluations;
+
+                if let Some(statistics) = report.statistics {
+                    self.simulation_result.history.push(statistics);
+                }
+
+                // The island has already called `new_fittest_found`.
+                if let Some(fittest) = report.fittest {
+                    if self.is_new_fittest(&fittest) {
+                        new_fittest_found = Some(index);
+                        fitness_counters[index] += 1;
+                        self.insert_fittest(fittest, population_ids[index], fitness_counters[index], 1);
+                    }
+                }
+
+                if let Some(reset_limit) = report.reset_limit {
+                    for observer in &mut self.observers {
+                        observer.on_population_reset(population_ids[index], reset_limit);
+                    }
+                }
+
+                for (target, migrant) in report.emigrants {
+                    for observer in &mut self.observers {
+                        observer.on_migration(population_ids[index], population_ids[target], &migrant);
+                    }
+                    // The target population may already have stopped.
+                    let _ = immigrant_senders[target].send(migrant);
+                }
+
+                num_of_reports += 1;
+                if num_of_reports < num_of_populations {
+                    continue;
+                }
+                num_of_reports = 0;
+
+                self.output_every_counter += 1;
+                self.share_counter += 1;
+                if let Some(source) = new_fittest_found.filter(|_| share_global && (self.share_counter >= self.share_every)) {
+                    let fittest = &self.simulation_result.fittest[0];
+
+                    for (sender, population_id) in immigrant_senders.iter().zip(&population_ids) {
+                        for observer in &mut self.observers {
+                            observer.on_migration(population_ids[source], *population_id, fittest);
+                        }
+                        let _ = sender.send(fittest.clone());
+                    }
+                    self.share_counter = 0;
+                }
+                new_fittest_found = None;
+
+                self.simulation_result.num_of_failures = num_of_failures.iter().sum();
+                self.simulation_result.num_of_evaluations = num_of_evaluations.iter().sum();
+                self.total_time_in_ms = start_time_in_ms + duration_in_ms(start_time.elapsed());
+                self.finish_iteration(1);
+
+                if self.is_finished() {
+                    stop.store(true, atomic::Ordering::Relaxed);
+                }
+            }
+
+            // The reports of the last, incomplete round.
+            self.simulation_result.num_of_failures = num_of_failures.iter().sum();
+            self.simulation_result.num_of_evaluations = num_of_evaluations.iter().sum();
+
+            handles.into_iter().map(|handle| handle.join().expect("population thread panicked")).collect()
+        });
+
+        for (population, fitness_counter) in self.habitat.iter_mut().zip(fitness_counters) {
+            population.fitness_counter = fitness_counter;
+        }
+
+        if self.multi_objective {
+            self.update_pareto_front();
+        }
+
+        self.total_time_in_ms = start_time_in_ms + duration_in_ms(start_time.elapsed());
+    }
+
+    /// Initializes the simulation: calculates the fitness of all individuals and the original
+    /// fitness. This is done automatically by `run` and `step`. If the simulation has already
+    /// been initialized (or restored from a checkpoint), it does nothing.
+    pub fn init(&mut self) {
+        if !self.simulation_result.fittest.is_empty() {
+            return;
+        }
+
         // Initialize timer
         let start_time = Instant::now();
 
@@ -107,74 +584,168 @@ impl<T: Individual + Send + Sync + Clone> Simulation<T> {
             population.calculate_fitness();
         }
 
-        let mut iteration_counter = 0;
-        let mut pool = make_pool(self.num_of_threads).unwrap();
-
         // Initialize:
         // - The fittest individual.
         // - The fitness at the beginning of the simulation. This is uesed to calculate the
         //   overall improvement later on.
+        // Skip individuals whose evaluation has failed, if possible.
+        let first = {
+            let population = &self.habitat[0].population;
+            population.iter().find(|wrapper| !wrapper.has_failed()).unwrap_or(&population[0]).clone()
+        };
+
         self.simulation_result = SimulationResult {
             improvement_factor: 0.0,
-            original_fitness: self.habitat[0].population[0].fitness,
-            fittest: vec![self.habitat[0].population[0].clone()],
-            iteration_counter: 0
+            original_fitness: first.fitness.clone(),
+            fittest: vec![first],
+            pareto_front: Vec::new(),
+            iteration_counter: 0,
+            num_of_failures: 0,
+            num_of_evaluations: 0,
+            last_improvement: 0,
+            history: Vec::new(),
         };
 
-        info!("original_fitness: {}", self.simulation_result.original_fitness);
+        self.simulation_result.num_of_evaluations = self.habitat.iter()
+            .map(|population| population.num_of_evaluations).sum();
 
-        // Check which type of simulation to run.
-        match self.type_of_simulation {
-            SimulationType::EndIteration(end_iteration) => {
-                for _ in 0..end_iteration {
-                    pool.scope(|scope|
-                        for population in &mut self.habitat {
-                            scope.submit(move || { population.run_body() });
-                        });
+        info!("original_fitness: {:?}", self.simulation_result.original_fitness);
 
-                    self.update_results();
-                };
-                self.simulation_result.iteration_counter = end_iteration;
-            }
+        self.add_time(start_time);
+    }
 
-            SimulationType::EndFactor(end_factor) => {
-                loop {
-                    iteration_counter += 1;
-                    pool.scope(|scope|
-                        for population in &mut self.habitat {
-                            scope.submit(move || { population.run_body() });
-                        });
+    /// Runs one epoch of the simulation on all populations and updates the results. An epoch
+    /// is one iteration, unless `iterations_per_epoch` is set in the `SimulationBuilder`.
+    /// The simulation is initialized first if needed. The stop condition is not checked, so
+    /// the simulation can be continued after it has finished.
+    /// Returns a snapshot of the progress.
+    pub fn step(&mut self) -> SimulationProgress<T::Fitness> {
+        if self.simulation_result.fittest.is_empty() {
+            self.init();
+        }
 
-                    self.update_results();
+        // Initialize timer
+        let start_time = Instant::now();
 
-                    if self.simulation_result.improvement_factor <= end_factor {
-                        break;
-                    }
-                };
-                self.simulation_result.iteration_counter = iteration_counter;
+        let num_of_threads = self.num_of_threads;
+        let executor = self.executor.get_or_insert_with(|| Arc::new(JobstealPool::new(num_of_threads))).clone();
+        let num_of_resets: Vec<u64> = self.habitat.iter().map(|population| population.num_of_resets).collect();
+        let iterations_per_epoch = self.iterations_per_epoch;
+        let per_individual = match self.scheduling {
+            Scheduling::PerPopulation => false,
+            Scheduling::PerIndividual => true,
+            Scheduling::Automatic => self.habitat.len() < executor.num_of_threads(),
+        };
+
+        if per_individual {
+            // The chunks of all populations are run together, so all threads are busy.
+            for _ in 0..iterations_per_epoch {
+                let variations: Vec<_> = self.habitat.iter_mut().map(|population| population.begin_iteration()).collect();
+                let mut offspring: Vec<_> = self.habitat.iter().map(|_| Vec::new()).collect();
+
+                let jobs: Vec<Job> = self.habitat.iter_mut().zip(variations.iter()).zip(offspring.iter_mut())
+                    .flat_map(|((population, variation), offspring)|
+                        population.variation_jobs(variation, executor.num_of_threads(), offspring))
+                    .collect();
+                executor.execute(jobs);
+
+                for ((population, variation), offspring) in self.habitat.iter_mut().zip(variations).zip(offspring) {
+                    population.end_iteration(variation, offspring);
+                }
             }
+        } else {
+            let jobs: Vec<Job> = self.habitat.iter_mut().map(|population| Box::new(move || {
+                for _ in 0..iterations_per_epoch {
+                    population.run_body();
+                }
+            }) as Job).collect();
+            executor.execute(jobs);
+        }
 
-            SimulationType::EndFitness(end_fitness) => {
-                loop {
-                    iteration_counter += 1;
-                    pool.scope(|scope|
-                        for population in &mut self.habitat {
-                            scope.submit(move || { population.run_body() });
-                        });
+        self.update_results(start_time, &num_of_resets);
+        self.add_time(start_time);
 
-                    self.update_results();
+        self.progress()
+    }
 
-                    if self.simulation_result.fittest[0].fitness <= end_fitness {
-                        break;
-                    }
-                };
-                self.simulation_result.iteration_counter = iteration_counter;
+    /// Runs up to `num_of_steps` epochs, but stops as soon as the simulation is finished.
+    /// Returns a snapshot of the progress after the last iteration.
+    pub fn step_n(&mut self, num_of_steps: u32) -> SimulationProgress<T::Fitness> {
+        self.init();
+
+        for _ in 0..num_of_steps {
+            if self.is_finished() {
+                break;
             }
-        } // End of match
+            self.step();
+        }
 
-        let elapsed = start_time.elapsed();
+        self.progress()
+    }
+
+    /// Returns true if the stop condition of the simulation (see `SimulationType`) is reached.
+    /// The stop conditions `EndFactor` and `EndFitness` need at least one iteration.
+    pub fn is_finished(&self) -> bool {
+        !self.simulation_result.fittest.is_empty() && self.criterion_reached(&self.type_of_simulation)
+    }
+
+    /// Returns a snapshot of the current progress of the simulation.
+    pub fn progress(&self) -> SimulationProgress<T::Fitness> {
+        let mut progress = self.snapshot();
+        progress.finished = self.is_finished();
+        progress
+    }
 
-        self.total_time_in_ms = elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1000_000.0;
+    /// The current progress, without checking the stop condition.
+    fn snapshot(&self) -> SimulationProgress<T::Fitness> {
+        SimulationProgress {
+            iteration: self.simulation_result.iteration_counter,
+            fitness: self.simulation_result.fittest.first()
+                .map_or_else(Default::default, |wrapper| wrapper.fitness.clone()),
+            improvement_factor: self.simulation_result.improvement_factor,
+            num_of_failures: self.simulation_result.num_of_failures,
+            num_of_evaluations: self.simulation_result.num_of_evaluations,
+            last_improvement: self.simulation_result.last_improvement,
+            total_time_in_ms: self.total_time_in_ms,
+            finished: false,
+        }
+    }
+
+    /// Check one (maybe composite) stop criterion.
+    fn criterion_reached(&self, criterion: &SimulationType<T::Fitness>) -> bool {
+        let result = &self.simulation_result;
+
+        match *criterion {
+            SimulationType::EndIteration(end_iteration) => result.iteration_counter >= end_iteration,
+            SimulationType::EndFactor(end_factor) => result.iteration_counter > 0 &&
+                result.fittest[0].is_feasible() && !self.direction.is_better(&end_factor, &result.improvement_factor),
+            SimulationType::EndFitness(ref end_fitness) => result.iteration_counter > 0 &&
+                result.fittest[0].is_feasible() && !self.direction.is_better(end_fitness, &result.fittest[0].fitness),
+            SimulationType::EndTime(end_time) => self.total_time_in_ms >= duration_in_ms(end_time),
+            SimulationType::EndEvaluations(end_evaluations) => result.num_of_evaluations >= end_evaluations,
+            SimulationType::EndStagnation(end_stagnation) =>
+                result.iteration_counter.saturating_sub(result.last_improvement) >= end_stagnation,
+            SimulationType::AnyOf(ref criteria) => criteria.iter().any(|criterion| self.criterion_reached(criterion)),
+            SimulationType::AllOf(ref criteria) => criteria.iter().all(|criterion| self.criterion_reached(criterion)),
+            SimulationType::Custom(ref criterion) => (criterion.0)(&self.snapshot()),
+        }
+    }
+
+    /// Add the time since `start_time` to the total run time.
+    fn add_time(&mut self, start_time: Instant) {
+        self.total_time_in_ms += duration_in_ms(start_time.elapsed());
+    }
+
+    /// Runs the simulation on a new background thread, like `run`. The returned handle can be
+    /// used to watch the progress, to pause, resume or stop the simulation and to get the
+    /// result when it has finished.
+    /// An `asynchronous` simulation can not be controlled this way, it returns an error.
+    pub fn spawn(self) -> Result<SimulationHandle<T>> where T: 'static {
+        if self.asynchronous {
+            return Err(ErrorKind::AsynchronousSpawn.into());
+        }
+
+        Ok(SimulationHandle::new(self))
     }
 
     /// This is a helper function that the user can call after the simulation stops in order to
@@ -182,7 +753,7 @@ impl<T: Individual + Send + Sync + Clone> Simulation<T> {
     /// improvement.
     pub fn print_fitness(&self) {
         for wrapper in &self.simulation_result.fittest {
-            info!("fitness: {}, num_of_mutations: {}, population: {}",
+            info!("fitness: {:?}, num_of_mutations: {}, population: {}",
                      wrapper.fitness, wrapper.num_of_mutations, wrapper.id);
         }
     }
@@ -190,45 +761,1240 @@ impl<T: Individual + Send + Sync + Clone> Simulation<T> {
     /// Update the internal state of the simulation: Has a new fittest individual been found ?
     /// Do we want to share it across all the other populations ?
     /// Also calculates the improvement factor.
-    fn update_results(&mut self) {
-        // Determine the fittest individual of all populations.
-        let mut new_fittest_found = false;
+    fn update_results(&mut self, start_time: Instant, num_of_resets: &[u64]) {
+        // Determine the fittest individual of all populations and the index of the population
+        // that has found it.
+        let mut new_fittest_found = None;
 
         // Increment the output counter
         // Only write an output if the max value output_every is reached
         self.output_every_counter += 1;
 
-        for population in &mut self.habitat {
-            if population.population[0].fitness < self.simulation_result.fittest[0].fitness {
-                new_fittest_found = true;
-                self.simulation_result.fittest.insert(0, population.population[0].clone());
-                // See https://github.com/willi-kappler/darwin-rs/issues/12
-                self.simulation_result.fittest.truncate(self.num_of_global_fittest);
+        let direction = self.direction;
+        let iterations_per_epoch = self.iterations_per_epoch;
+
+        for (population_index, previous_num_of_resets) in num_of_resets.iter().enumerate() {
+            // In a multi-objective optimization or with a penalty function the population is not
+            // sorted by feasibility and fitness.
+            let index = fittest_index(&self.habitat[population_index].population, direction);
+
+            // There is no valid individual in this population.
+            if !self.habitat[population_index].population[index].valid {
+                continue;
+            }
+
+            if self.is_new_fittest(&self.habitat[population_index].population[index]) {
+                new_fittest_found = Some(population_index);
+
+                let population = &mut self.habitat[population_index];
                 population.fitness_counter += 1;
-                if self.output_every_counter >= self.output_every {
-                    info!("new fittest: fitness: {}, population id: {}, counter: {}", population.population[0].fitness,population.id,
-                        population.fitness_counter);
-                    self.output_every_counter = 0
-                }
+                let fittest = population.population[index].clone();
                 // Call methond `new_fittest_found` of the newly found fittest individual.
                 // The default implementation for this method does nothing.
-                population.population[0].individual.new_fittest_found();
+                population.population[index].individual.new_fittest_found();
+
+                let (population_id, fitness_counter) = (population.id, population.fitness_counter);
+                self.insert_fittest(fittest, population_id, fitness_counter, iterations_per_epoch);
+            }
+
+            let population = &self.habitat[population_index];
+            if population.num_of_resets > *previous_num_of_resets {
+                for observer in &mut self.observers {
+                    observer.on_population_reset(population.id, population.reset_limit);
+                }
             }
         }
 
+        if self.multi_objective {
+            self.update_pareto_front();
+        }
+
         // Now copy the most fittest individual back to each population
         // if the user has specified it and the share_every count is reached
-        self.share_counter += 1;
-        if self.share_fittest && new_fittest_found && (self.share_counter >= self.share_every) {
-            for population in &mut self.habitat {
-                population.population[0] = self.simulation_result.fittest[0].clone();
-            }
+        self.share_counter += iterations_per_epoch;
+        if self.share_fittest && self.migration_topology != MigrationTopology::Global {
+            self.migrate();
+        } else if let Some(source) = new_fittest_found.filter(|_| self.share_fittest && (self.share_counter >= self.share_every)) {
+            let migrants = vec![vec![(source, self.simulation_result.fittest[0].clone())]; self.habitat.len()];
+            let holes = vec![Vec::new(); self.habitat.len()];
+
+            self.insert_migrants(migrants, holes);
             self.share_counter = 0;
         }
 
+        self.simulation_result.num_of_failures = self.habitat.iter()
+            .map(|population| population.num_of_failures).sum();
+        self.simulation_result.num_of_evaluations = self.habitat.iter()
+            .map(|population| population.num_of_evaluations).sum();
+
+        if self.record_history {
+            let time_in_ms = self.total_time_in_ms + duration_in_ms(start_time.elapsed());
+
+            for population in &self.habitat {
+                self.simulation_result.history.push(
+                    IterationStatistics::new(population, self.simulation_result.iteration_counter + iterations_per_epoch, time_in_ms));
+            }
+        }
+
+        self.finish_iteration(iterations_per_epoch);
+        self.save_automatic_checkpoint(false);
+    }
+
+    /// Returns true if the given (valid) individual is better than the fittest individual of the
+    /// whole simulation.
+    fn is_new_fittest(&self, candidate: &IndividualWrapper<T>) -> bool {
+        candidate.compare_fitness(&self.simulation_result.fittest[0], self.direction) == Ordering::Less
+    }
+
+    /// Inserts a new fittest individual into the global "high score list" and notifies the
+    /// observers. `fitness_counter` is the one of the population that has found it,
+    /// `num_of_iterations` is the number of iterations since the last update.
+    fn insert_fittest(&mut self, fittest: IndividualWrapper<T>, population_id: u32, fitness_counter: u64, num_of_iterations: u32) {
+        self.simulation_result.last_improvement = self.simulation_result.iteration_counter + num_of_iterations;
+
+        if self.output_every_counter >= self.output_every {
+            info!("new fittest: fitness: {:?}, population id: {}, counter: {}", fittest.fitness, population_id,
+                fitness_counter);
+            self.output_every_counter = 0
+        }
+
+        for observer in &mut self.observers {
+            observer.on_new_fittest(population_id, &fittest);
+        }
+
+        self.simulation_result.fittest.insert(0, fittest);
+        // See https://github.com/willi-kappler/darwin-rs/issues/12
+        self.simulation_result.fittest.truncate(self.num_of_global_fittest);
+    }
+
+    /// Updates the improvement factor, increments the iteration counter by `num_of_iterations`
+    /// and notifies the observers.
+    fn finish_iteration(&mut self, num_of_iterations: u32) {
         self.simulation_result.improvement_factor =
-            self.simulation_result.fittest[0].fitness /
-            self.simulation_result.original_fitness;
+            self.simulation_result.fittest[0].fitness.to_f64() /
+            self.simulation_result.original_fitness.to_f64();
+
+        self.simulation_result.iteration_counter += num_of_iterations;
+
+        for observer in &mut self.observers {
+            observer.on_iteration(&self.simulation_result, self.simulation_result.iteration_counter);
+        }
+    }
+
+    /// Along each edge of the migration topology whose interval is reached in this epoch,
+    /// individuals migrate from the source population to the target population according to the
+    /// migration policy. The emigrants are chosen before any immigrant is inserted.
+    fn migrate(&mut self) {
+        let iteration = self.simulation_result.iteration_counter + self.iterations_per_epoch;
+        let iterations_per_epoch = self.iterations_per_epoch;
+        let direction = self.direction;
+        let mut migrants = vec![Vec::new(); self.habitat.len()];
+        let mut holes = vec![Vec::new(); self.habitat.len()];
+
+        for edge in self.migration_edges.iter().filter(|edge| interval_reached(iteration, iterations_per_epoch, edge.interval)) {
+            let source = &self.habitat[edge.source];
+            let policy = source.migration_policy.as_ref().unwrap_or(&self.migration_policy);
+
+            for index in policy.select_emigrants(&source.population, direction, &mut self.migration_rng) {
+                migrants[edge.target].push((edge.source, source.population[index].clone()));
+
+                // Keep at least one individual in the source population.
+                if policy.move_migrants && !holes[edge.source].contains(&index) &&
+                    holes[edge.source].len() + 1 < source.population.len() {
+                    holes[edge.source].push(index);
+                }
+            }
+        }
+
+        self.insert_migrants(migrants, holes);
+    }
+
+    /// Inserts the immigrants (and the index of the population they come from) into each
+    /// population. They take the places of the emigrants that have left the population (`holes`)
+    /// first, then they replace individuals according to the migration policy of the population.
+    /// The remaining holes are removed.
+    fn insert_migrants(&mut self, migrants: Vec<Vec<(usize, IndividualWrapper<T>)>>, holes: Vec<Vec<usize>>) {
+        let direction = self.direction;
+        let population_ids: Vec<u32> = self.habitat.iter().map(|population| population.id).collect();
+
+        for ((population, migrants), holes) in self.habitat.iter_mut().zip(migrants).zip(holes) {
+            let (sources, immigrants): (Vec<usize>, Vec<IndividualWrapper<T>>) = migrants.into_iter().unzip();
+            let policy = population.migration_policy.as_ref().unwrap_or(&self.migration_policy);
+            let num_of_filled = holes.len().min(immigrants.len());
+            let replaced = policy.select_replaced(&population.population, &immigrants[num_of_filled..],
+                &holes, direction, &mut self.migration_rng);
+
+            let places: Vec<usize> = holes[..num_of_filled].iter().cloned().chain(replaced).collect();
+
+            for ((index, source), immigrant) in places.into_iter().zip(sources).zip(immigrants) {
+                for observer in &mut self.observers {
+                    observer.on_migration(population_ids[source], population.id, &immigrant);
+                }
+
+                population.population[index] = immigrant;
+                population.num_of_migrations += 1;
+            }
+
+            let mut unfilled = holes[num_of_filled..].to_vec();
+            unfilled.sort();
+            for index in unfilled.into_iter().rev() {
+                population.population.remove(index);
+            }
+        }
+    }
+
+    /// Merge the feasible non-dominated individuals of all populations into the Pareto front of the
+    /// simulation result. If the front gets too big, the individuals in the most crowded
+    /// regions are removed.
+    fn update_pareto_front(&mut self) {
+        let mut candidates = mem::take(&mut self.simulation_result.pareto_front);
+
+        for population in &self.habitat {
+            // Only feasible individuals can be part of the Pareto front.
+            let feasible: Vec<&IndividualWrapper<T>> = population.population.iter()
+                .filter(|wrapper| wrapper.is_feasible()).collect();
+            let objectives: Vec<&[f64]> = feasible.iter()
+                .map(|wrapper| &wrapper.objectives[..]).collect();
+            let ranks = pareto::non_dominated_sort(&objectives, self.direction);
+
+            candidates.extend(feasible.iter().zip(ranks)
+                .filter(|&(_, rank)| rank == 0).map(|(wrapper, _)| (*wrapper).clone()));
+        }
+
+        let mut front: Vec<IndividualWrapper<T>> = {
+            let objectives: Vec<&[f64]> = candidates.iter().map(|wrapper| &wrapper.objectives[..]).collect();
+            let ranks = pareto::non_dominated_sort(&objectives, self.direction);
+
+            candidates.iter().zip(ranks).filter(|&(_, rank)| rank == 0)
+                .map(|(wrapper, _)| wrapper.clone()).collect()
+        };
+
+        // Remove individuals with the same objectives, keep the older ones.
+        let mut index = 0;
+        while index < front.len() {
+            if front[..index].iter().any(|wrapper| wrapper.objectives == front[index].objectives) {
+                front.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        if front.len() > self.pareto_front_size {
+            let mut distance = {
+                let objectives: Vec<&[f64]> = front.iter().map(|wrapper| &wrapper.objectives[..]).collect();
+                pareto::crowding_distance(&objectives, &vec![0; front.len()])
+            };
+
+            while front.len() > self.pareto_front_size {
+                // Remove the most crowded individual and recalculate the distance.
+                let most_crowded = (0..front.len()).min_by(|a, b|
+                    pareto::compare(0, distance[*b], 0, distance[*a])).unwrap();
+                front.remove(most_crowded);
+
+                let objectives: Vec<&[f64]> = front.iter().map(|wrapper| &wrapper.objectives[..]).collect();
+                distance = pareto::crowding_distance(&objectives, &vec![0; front.len()]);
+            }
+        }
+
+        self.simulation_result.pareto_front = front;
+    }
+
+    /// Save the simulation to the checkpoint file, if the user has specified it and
+    /// the number of iterations is reached (or `force` is set).
+    /// An error is only written to the log, since the simulation itself can still continue.
+    pub(crate) fn save_automatic_checkpoint(&self, force: bool) {
+        if let Some(ref checkpoint) = self.checkpoint {
+            if force || (checkpoint.every > 0 && interval_reached(self.simulation_result.iteration_counter,
+                         self.iterations_per_epoch, checkpoint.every)) {
+                if let Err(e) = (checkpoint.save)(self, &checkpoint.path) {
+                    error!("could not save checkpoint: {}, error: {}", checkpoint.path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Individual + Send + Sync + Clone + Serialize> Simulation<T> {
+    /// Save the whole simulation (all populations, the results and the iteration counter) to
+    /// the given file. The simulation can be restored with `from_checkpoint` in the
+    /// `SimulationBuilder` and then continues where it stopped.
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<()> where T::Fitness: Serialize {
+        let file = File::create(path).map_err(|e| ErrorKind::CheckpointWrite(e.to_string()))?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|e| ErrorKind::CheckpointWrite(e.to_string()).into())
+    }
+}
+
+impl<T: Individual + Send + Sync + Clone + DeserializeOwned> Simulation<T> {
+    /// Load a simulation from the given checkpoint file that has been written with
+    /// `save_checkpoint`. Use `from_checkpoint` in the `SimulationBuilder` instead, if you want
+    /// to change the configuration of the simulation before it continues. This fails if a
+    /// population uses a user defined selection strategy or tie break or if the simulation
+    /// uses a user defined stop criterion, since these can only be restored with the
+    /// `SimulationBuilder`.
+    pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> Result<Simulation<T>> where T::Fitness: DeserializeOwned {
+        let simulation = Simulation::read_checkpoint(path)?;
+
+        if simulation.type_of_simulation.is_custom() {
+            return Err(ErrorKind::CheckpointRead(
+                "user defined strategy can not be restored: termination".to_string()).into());
+        }
+        if let Some(&(population_id, strategy)) = simulation.unrestored_strategies().first() {
+            return Err(ErrorKind::CheckpointRead(format!(
+                "user defined strategy can not be restored: population {}, {}", population_id, strategy)).into());
+        }
+
+        Ok(simulation)
+    }
+
+    /// Reads the checkpoint file, user defined strategies are not restored yet.
+    pub(crate) fn read_checkpoint<P: AsRef<Path>>(path: P) -> Result<Simulation<T>> where T::Fitness: DeserializeOwned {
+        let file = File::open(path).map_err(|e| ErrorKind::CheckpointRead(e.to_string()))?;
+        let simulation: Simulation<T> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| ErrorKind::CheckpointRead(e.to_string()))?;
+
+        info!("continue simulation at iteration: {}", simulation.simulation_result.iteration_counter);
+        Ok(simulation)
+    }
+
+    /// The user defined strategies (population id, name) that could not be restored from
+    /// the checkpoint.
+    pub(crate) fn unrestored_strategies(&self) -> Vec<(u32, &'static str)> {
+        self.habitat.iter().flat_map(|population| population.unrestored_strategies().into_iter()
+            .map(move |strategy| (population.id, strategy))).collect()
+    }
+}
+
+/// The index of the fittest individual, the population may not be sorted (in a multi-objective
+/// optimization or with a penalty function).
+pub(crate) fn fittest_index<T: Individual>(candidates: &[IndividualWrapper<T>], direction: Direction) -> usize {
+    (0..candidates.len()).min_by(|a, b| candidates[*a].compare_fitness(&candidates[*b], direction)).unwrap()
+}
+
+/// Returns true if a multiple of `interval` has been reached in the last `num_of_iterations`
+/// iterations, that is between `iteration - num_of_iterations` (exclusive) and `iteration`.
+fn interval_reached(iteration: u32, num_of_iterations: u32, interval: u32) -> bool {
+    iteration / interval > iteration.saturating_sub(num_of_iterations) / interval
+}
+
+/// Converts a duration to milliseconds.
+pub(crate) fn duration_in_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{self, AtomicUsize};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use rand::Rng;
+
+    use executor::{Executor, JobstealPool, SharedPool, Sequential};
+    use individual::{Individual, IndividualWrapper};
+    use migration::{MigrationTopology, MigrationEdge, MigrationPolicy};
+    use observer::Observer;
+    use pareto;
+    use population::{Population, TieBreak};
+    use population_builder::PopulationBuilder;
+    use random::DarwinRng;
+    use selection::{Selection, BuiltInSelection, Tournament, RankBased};
+    use simulation::{Simulation, Direction, Scheduling, SimulationResult, SimulationType, interval_reached};
+    use simulation_builder::{self, SimulationBuilder};
+
+    #[derive(Clone,Serialize,Deserialize)]
+    struct IndividualTest1 {
+        value: u32,
+    }
+
+    impl Individual for IndividualTest1 {
+        type Fitness = u32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            if self.value > 0 {
+                self.value -= 1;
+            }
+        }
+
+        fn calculate_fitness(&mut self) -> u32 {
+            self.value
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 100;
+        }
+    }
+
+    /// Creates a population with the given id and four copies of the individual, without the
+    /// fixed reset schedule.
+    fn make_population<T: Individual + Send + Sync + Clone>(id: u32, individual: T) -> Population<T> {
+        PopulationBuilder::<T>::new()
+            .set_id(id)
+            .initial_population(&vec![individual; 4])
+            .reset_limit_end(0)
+            .finalize().unwrap()
+    }
+
+    /// A user defined selection strategy, it can not be saved in a checkpoint.
+    struct Worst;
+
+    impl Selection for Worst {
+        fn select(&self, fitness: &[f64], num_of_selected: usize, _rng: &mut DarwinRng) -> Vec<usize> {
+            vec![fitness.len() - 1; num_of_selected]
+        }
+    }
+
+    #[derive(Clone,Serialize,Deserialize)]
+    struct IndividualTest3;
+
+    impl Individual for IndividualTest3 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            0.0
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+        }
+    }
+
+    #[derive(Clone,Serialize,Deserialize)]
+    struct IndividualTest4 {
+        value: u32,
+        found: u32,
+    }
+
+    impl Individual for IndividualTest4 {
+        type Fitness = u32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.value = self.value.saturating_sub(1);
+        }
+
+        fn calculate_fitness(&mut self) -> u32 {
+            self.value
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.value = 100;
+        }
+
+        fn new_fittest_found(&mut self) {
+            self.found += 1;
+        }
+    }
+
+    /// The number of mutated `IndividualTest5` that are evaluated right now, and the maximum
+    /// of it.
+    static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+    static MAX_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone)]
+    struct IndividualTest5 {
+        mutated: bool,
+    }
+
+    impl Individual for IndividualTest5 {
+        type Fitness = u32;
+
+        fn mutate(&mut self, _rng: &mut DarwinRng) {
+            self.mutated = true;
+        }
+
+        // Only the evaluations in the jobs of the executor are counted, not the first one
+        // in `init`. Each evaluation waits until two of them have run at the same time, so the
+        // result does not depend on the timing of the threads.
+        fn calculate_fitness(&mut self) -> u32 {
+            if self.mutated {
+                let active = ACTIVE.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+                MAX_ACTIVE.fetch_max(active, atomic::Ordering::SeqCst);
+                let start_time = Instant::now();
+                while MAX_ACTIVE.load(atomic::Ordering::SeqCst) < 2 && start_time.elapsed() < Duration::from_secs(10) {
+                    thread::yield_now();
+                }
+                ACTIVE.fetch_sub(1, atomic::Ordering::SeqCst);
+            }
+            1
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+        }
+    }
+
+    #[derive(Clone)]
+    struct IndividualTest2 {
+        x: f64,
+        objectives: Vec<f64>,
+    }
+
+    impl Individual for IndividualTest2 {
+        type Fitness = f64;
+
+        fn mutate(&mut self, rng: &mut DarwinRng) {
+            self.x += rng.gen_range(-1.0, 1.0);
+        }
+
+        fn calculate_fitness(&mut self) -> f64 {
+            self.objectives = vec![self.x * self.x, (self.x - 2.0) * (self.x - 2.0)];
+            self.objectives[0]
+        }
+
+        fn reset(&mut self, _rng: &mut DarwinRng) {
+            self.x = 10.0;
+        }
+
+        fn objectives(&self) -> Vec<f64> {
+            self.objectives.clone()
+        }
+    }
+
+    #[test]
+    fn checkpoint1() {
+        let path = env::temp_dir().join("darwin_rs_checkpoint1.json");
+
+        let population = make_population(1, IndividualTest1 { value: 100 });
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .add_population(population)
+            .checkpoint(&path, 5)
+            .finalize().unwrap();
+
+        simulation.run();
+
+        let mut restored = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .iterations(20)
+            .finalize().unwrap();
+
+        assert_eq!(restored.simulation_result.iteration_counter, 10);
+        assert_eq!(restored.simulation_result.fittest[0].fitness, 90);
+
+        restored.run();
+
+        assert_eq!(restored.simulation_result.iteration_counter, 20);
+        assert_eq!(restored.simulation_result.fittest[0].fitness, 80);
+        assert_eq!(restored.simulation_result.original_fitness, 100);
+    }
+
+    #[test]
+    fn checkpoint2() {
+        let path = env::temp_dir().join("darwin_rs_checkpoint2.json");
+
+        let population = make_population(1, IndividualTest3);
+
+        let mut simulation = SimulationBuilder::<IndividualTest3>::new()
+            .iterations(10)
+            .threads(1)
+            .record_history()
+            .add_population(population)
+            .finalize().unwrap();
+
+        simulation.run();
+        simulation.habitat[0].population[3].fitness = f64::NAN;
+        simulation.habitat[0].population[3].objectives = vec![f64::INFINITY, f64::NEG_INFINITY];
+        simulation.simulation_result.history[0].mean_fitness = f64::NAN;
+
+        // The original fitness is zero, so the improvement factor is NaN.
+        assert!(simulation.simulation_result.improvement_factor.is_nan());
+        simulation.save_checkpoint(&path).unwrap();
+
+        let mut restored = SimulationBuilder::<IndividualTest3>::from_checkpoint(&path).unwrap()
+            .iterations(20)
+            .finalize().unwrap();
+
+        assert!(restored.habitat[0].population[3].fitness.is_nan());
+        assert_eq!(restored.habitat[0].population[3].objectives, vec![f64::INFINITY, f64::NEG_INFINITY]);
+        assert!(restored.simulation_result.history[0].mean_fitness.is_nan());
+        assert!(restored.simulation_result.improvement_factor.is_nan());
+        assert_eq!(restored.simulation_result.original_fitness, 0.0);
+
+        restored.run();
+        assert_eq!(restored.simulation_result.iteration_counter, 20);
+
+        restored.habitat[0].population[0].fitness = f64::INFINITY;
+        restored.habitat[0].population[1].fitness = f64::NEG_INFINITY;
+        restored.save_checkpoint(&path).unwrap();
+
+        let restored = SimulationBuilder::<IndividualTest3>::from_checkpoint(&path).unwrap()
+            .finalize().unwrap();
+        assert_eq!(restored.habitat[0].population[0].fitness, f64::INFINITY);
+        assert_eq!(restored.habitat[0].population[1].fitness, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn checkpoint3() {
+        let path = env::temp_dir().join("darwin_rs_checkpoint3.json");
+
+        let population1 = PopulationBuilder::<IndividualTest1>::new()
+            .initial_population(&vec![IndividualTest1 { value: 100 }; 4])
+            .selection(RankBased{ selection_pressure: 1.5 })
+            .parent_selection(Tournament{ size: 3 })
+            .tie_break(TieBreak::PreferParent)
+            .reset_limit_end(0)
+            .finalize().unwrap();
+
+        let population2 = PopulationBuilder::<IndividualTest1>::new()
+            .initial_population(&vec![IndividualTest1 { value: 100 }; 4])
+            .parent_selection(Worst)
+            .tie_break(TieBreak::Custom(|a, b| a.value.cmp(&b.value)))
+            .set_id(2)
+            .reset_limit_end(0)
+            .finalize().unwrap();
+
+        let simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .add_population(population1)
+            .add_population(population2)
+            .finalize().unwrap();
+
+        simulation.save_checkpoint(&path).unwrap();
+
+        // The user defined strategies of the second population are missing.
+        assert!(Simulation::<IndividualTest1>::load_checkpoint(&path).is_err());
+        assert!(SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .restore_parent_selection(2, Worst)
+            .finalize().is_err());
+
+        let restored = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .restore_parent_selection(2, Worst)
+            .restore_tie_break(2, TieBreak::Custom(|a, b| a.value.cmp(&b.value)))
+            .finalize().unwrap();
+
+        let population = &restored.habitat[0];
+        assert_eq!(population.selection.built_in(), Some(BuiltInSelection::RankBased{ selection_pressure: 1.5 }));
+        assert_eq!(population.parent_selection.built_in(), Some(BuiltInSelection::Tournament{ size: 3 }));
+        assert!(matches!(population.tie_break, TieBreak::PreferParent));
+        assert_eq!(restored.habitat[1].selection.built_in(), Some(BuiltInSelection::Truncation));
+    }
+
+    #[test]
+    fn checkpoint4() {
+        let path = env::temp_dir().join("darwin_rs_checkpoint4.json");
+
+        let mut builder = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .seed(7)
+            .migration_topology(MigrationTopology::RandomNeighbours(1));
+        for id in 1..5 {
+            builder = builder.add_population(make_population(id, IndividualTest1 { value: 100 }));
+        }
+        let mut simulation = builder.finalize().unwrap();
+        simulation.step();
+        simulation.save_checkpoint(&path).unwrap();
+
+        // The random topology is not created anew, the random number generator continues.
+        let restored = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .finalize().unwrap();
+        assert_eq!(restored.migration_edges, simulation.migration_edges);
+        assert_eq!(restored.migration_rng, simulation.migration_rng);
+
+        let changed = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .migration_topology(MigrationTopology::Ring)
+            .finalize().unwrap();
+        assert_eq!(changed.migration_edges.len(), 4);
+    }
+
+    #[test]
+    fn maximize1() {
+        let population = make_population(1, IndividualTest1 { value: 100 });
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .direction(Direction::Maximize)
+            .add_population(population)
+            .finalize().unwrap();
+
+        simulation.run();
+
+        // Mutation only decreases the fitness, so the original individuals are the fittest.
+        assert_eq!(simulation.simulation_result.fittest[0].fitness, 100);
+        assert_eq!(simulation.habitat[0].population[3].fitness, 100);
+        assert_eq!(simulation.simulation_result.improvement_factor, 1.0);
+    }
+
+    #[test]
+    fn multi_objective1() {
+        let population = PopulationBuilder::<IndividualTest2>::new()
+            .initial_population(&vec![IndividualTest2 { x: 10.0, objectives: Vec::new() }; 10])
+            .reset_limit_end(0)
+            .finalize().unwrap();
+
+        let mut simulation = SimulationBuilder::<IndividualTest2>::new()
+            .iterations(50)
+            .threads(1)
+            .multi_objective()
+            .pareto_front_size(5)
+            .add_population(population)
+            .finalize().unwrap();
+
+        simulation.run();
+
+        let front = &simulation.simulation_result.pareto_front;
+
+        assert!(!front.is_empty());
+        assert!(front.len() <= 5);
+
+        for wrapper1 in front {
+            for wrapper2 in front {
+                assert!(!pareto::dominates(&wrapper1.objectives, &wrapper2.objectives, Direction::Minimize));
+            }
+        }
+    }
+
+    fn seeded_builder(seed: u64) -> SimulationBuilder<IndividualTest2> {
+        let populations = (1..5).map(|id| PopulationBuilder::<IndividualTest2>::new()
+            .set_id(id)
+            .initial_population(&vec![IndividualTest2 { x: 10.0, objectives: Vec::new() }; 5])
+            .reset_limit_end(0)
+            .finalize().unwrap()).collect();
+
+        SimulationBuilder::<IndividualTest2>::new()
+            .iterations(20)
+            .seed(seed)
+            .add_multiple_populations(populations)
+    }
+
+    fn run_to_end(builder: SimulationBuilder<IndividualTest2>) -> Vec<f64> {
+        let mut simulation = builder.finalize().unwrap();
+        simulation.run();
+
+        simulation.habitat.iter().flat_map(|population|
+            population.population.iter().map(|wrapper| wrapper.individual.x)).collect()
+    }
+
+    fn run_seeded(seed: u64, threads: usize, scheduling: Scheduling) -> Vec<f64> {
+        run_to_end(seeded_builder(seed).threads(threads).scheduling(scheduling))
+    }
+
+    #[test]
+    fn seed1() {
+        // The result does not depend on the number of threads.
+        assert_eq!(run_seeded(7, 1, Scheduling::Automatic), run_seeded(7, 4, Scheduling::Automatic));
+        assert!(run_seeded(7, 1, Scheduling::Automatic) != run_seeded(8, 1, Scheduling::Automatic));
+    }
+
+    #[test]
+    fn scheduling1() {
+        // The result does not depend on the scheduling.
+        let expected = run_seeded(7, 4, Scheduling::PerPopulation);
+        assert_eq!(run_seeded(7, 4, Scheduling::PerIndividual), expected);
+        assert_eq!(run_seeded(7, 3, Scheduling::PerIndividual), expected);
+        assert_eq!(run_seeded(7, 8, Scheduling::Automatic), expected);
+    }
+
+    #[test]
+    fn executor1() {
+        let expected = run_seeded(7, 2, Scheduling::PerPopulation);
+        assert_eq!(run_to_end(seeded_builder(7).executor(Arc::new(Sequential))
+            .scheduling(Scheduling::PerIndividual)), expected);
+        assert_eq!(run_to_end(seeded_builder(7).executor(Arc::new(SharedPool::new(3)))), expected);
+
+        // Two simulations share one thread pool.
+        let executor: Arc<dyn Executor> = Arc::new(JobstealPool::new(2));
+        let handles: Vec<_> = (0..2).map(|_| {
+            let executor = executor.clone();
+            thread::spawn(move || run_to_end(seeded_builder(7).executor(executor)))
+        }).collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn executor2() {
+        // Each simulation has one population, so it runs one job at a time. The executor has
+        // two threads, the simulations share them and run at the same time.
+        let executor: Arc<dyn Executor> = Arc::new(SharedPool::new(2));
+
+        let handles: Vec<_> = (0..2).map(|_| {
+            let executor = executor.clone();
+
+            thread::spawn(move || {
+                let population = PopulationBuilder::<IndividualTest5>::new()
+                    .initial_population(&vec![IndividualTest5 { mutated: false }; 3])
+                    .reset_limit_end(0)
+                    .finalize().unwrap();
+
+                SimulationBuilder::<IndividualTest5>::new()
+                    .iterations(20)
+                    .add_population(population)
+                    .scheduling(Scheduling::PerPopulation)
+                    .executor(executor)
+                    .finalize().unwrap()
+                    .run();
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(MAX_ACTIVE.load(atomic::Ordering::SeqCst), 2);
+    }
+
+    struct TestObserver {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Observer<IndividualTest1> for TestObserver {
+        fn on_iteration(&mut self, _result: &SimulationResult<IndividualTest1>, iteration: u32) {
+            self.events.lock().unwrap().push(format!("iteration {}", iteration));
+        }
+
+        fn on_new_fittest(&mut self, _population_id: u32, _fittest: &IndividualWrapper<IndividualTest1>) {
+            self.events.lock().unwrap().push("fittest".to_string());
+        }
+
+        fn on_population_reset(&mut self, _population_id: u32, _new_reset_limit: u32) {
+            self.events.lock().unwrap().push("reset".to_string());
+        }
+
+        fn on_migration(&mut self, source_id: u32, target_id: u32, migrant: &IndividualWrapper<IndividualTest1>) {
+            self.events.lock().unwrap().push(format!("migration {} {} {}", source_id, target_id, migrant.id));
+        }
+    }
+
+    #[test]
+    fn observer1() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let populations = (1..3).map(|id| PopulationBuilder::<IndividualTest1>::new()
+            .set_id(id)
+            .initial_population(&vec![IndividualTest1 { value: 100 }; 4])
+            .reset_limit_start(3)
+            .reset_limit_increment(3)
+            .reset_limit_end(100)
+            .finalize().unwrap()).collect();
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .share_fittest()
+            .share_every(1)
+            .add_multiple_populations(populations)
+            .observer(TestObserver { events: events.clone() })
+            .finalize().unwrap();
+
+        simulation.run();
+
+        let events = events.lock().unwrap();
+        let count = |name: &str| events.iter().filter(|event| event.starts_with(name)).count();
+
+        assert_eq!(count("iteration"), 10);
+        assert_eq!(events.last().unwrap(), "iteration 10");
+        assert!(count("fittest") > 0);
+        assert!(count("reset") > 0);
+        assert!(count("migration") > 0);
+    }
+
+    #[test]
+    fn observer2() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        // The fittest individual of population 1 migrates to population 2 and from there on to
+        // population 3.
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(1)
+            .share_fittest()
+            .migration_topology(MigrationTopology::Custom(vec![
+                MigrationEdge { source: 0, target: 1, interval: 1 },
+                MigrationEdge { source: 1, target: 2, interval: 1 },
+            ]))
+            .add_population(make_population(1, IndividualTest1 { value: 10 }))
+            .add_population(make_population(2, IndividualTest1 { value: 100 }))
+            .add_population(make_population(3, IndividualTest1 { value: 100 }))
+            .observer(TestObserver { events: events.clone() })
+            .finalize().unwrap();
+
+        simulation.run();
+
+        let events = events.lock().unwrap();
+        assert!(events.contains(&"migration 1 2 1".to_string()));
+        assert!(events.contains(&"migration 2 3 1".to_string()));
+        assert!(!events.iter().any(|event| event.starts_with("migration 1 3")));
+    }
+
+    #[test]
+    fn step1() {
+        let population = make_population(1, IndividualTest1 { value: 100 });
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .threads(2)
+            .add_population(population)
+            .finalize().unwrap();
+
+        simulation.init();
+        assert_eq!(simulation.progress().fitness, 100);
+
+        let progress = simulation.step();
+        assert_eq!(progress.iteration, 1);
+        assert_eq!(progress.fitness, 99);
+        assert!(!progress.finished);
+
+        let progress = simulation.step_n(100);
+        assert_eq!(progress.iteration, 10);
+        assert_eq!(progress.fitness, 90);
+        assert!(progress.finished);
+
+        // A finished simulation can still be continued.
+        assert_eq!(simulation.step().iteration, 11);
+    }
+
+    #[test]
+    fn termination1() {
+        // The fitness 0 is reached in iteration 5, then there is no more improvement.
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .termination(SimulationType::AnyOf(vec![
+                SimulationType::EndStagnation(3),
+                SimulationType::EndIteration(1000),
+            ]))
+            .add_population(make_population(1, IndividualTest1 { value: 5 }))
+            .finalize().unwrap();
+
+        simulation.run();
+        assert_eq!(simulation.simulation_result.last_improvement, 5);
+        assert_eq!(simulation.simulation_result.iteration_counter, 8);
+
+        // 4 evaluations at the start and 4 in each iteration.
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .termination(SimulationType::AllOf(vec![
+                SimulationType::EndEvaluations(20),
+                SimulationType::custom(|progress| progress.iteration % 3 == 0),
+            ]))
+            .add_population(make_population(1, IndividualTest1 { value: 5 }))
+            .finalize().unwrap();
+
+        simulation.run();
+        assert_eq!(simulation.simulation_result.num_of_evaluations, 28);
+        assert_eq!(simulation.simulation_result.iteration_counter, 6);
+
+        let result = SimulationBuilder::<IndividualTest1>::new()
+            .termination(SimulationType::AnyOf(vec![]))
+            .add_population(make_population(1, IndividualTest1 { value: 5 }))
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::EmptyTermination, _)) => {}
+            _ => panic!("empty termination not detected"),
+        }
+
+        let result = SimulationBuilder::<IndividualTest1>::new()
+            .termination(SimulationType::AllOf(vec![SimulationType::EndIteration(5)]))
+            .add_population(make_population(1, IndividualTest1 { value: 5 }))
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::EndIterationTooLow, _)) => {}
+            _ => panic!("nested end iteration not checked"),
+        }
+    }
+
+    #[test]
+    fn termination2() {
+        let path = env::temp_dir().join("darwin_rs_termination2.json");
+
+        let simulation = SimulationBuilder::<IndividualTest1>::new()
+            .termination(SimulationType::AnyOf(vec![
+                SimulationType::EndIteration(1000),
+                SimulationType::custom(|progress| progress.iteration == 5),
+            ]))
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .finalize().unwrap();
+
+        simulation.save_checkpoint(&path).unwrap();
+
+        // The user defined stop criterion is missing.
+        assert!(Simulation::<IndividualTest1>::load_checkpoint(&path).is_err());
+        let result = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::StrategyNotRestored(_), _)) => {}
+            _ => panic!("unrestored stop criterion not detected"),
+        }
+
+        let mut restored = SimulationBuilder::<IndividualTest1>::from_checkpoint(&path).unwrap()
+            .termination(SimulationType::custom(|progress| progress.iteration == 5))
+            .finalize().unwrap();
+
+        restored.run();
+        assert_eq!(restored.simulation_result.iteration_counter, 5);
+    }
+
+    #[test]
+    fn history1() {
+        let path = env::temp_dir().join("darwin_rs_history1.csv");
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .add_population(make_population(2, IndividualTest1 { value: 100 }))
+            .record_history()
+            .finalize().unwrap();
+
+        simulation.run();
+
+        let history = &simulation.simulation_result.history;
+        assert_eq!(history.len(), 20);
+        assert_eq!(history[0].iteration, 1);
+        assert_eq!(history[1].population_id, 2);
+        assert_eq!(history[19].iteration, 10);
+        assert_eq!(history[19].best_fitness, 90.0);
+        assert!(history[19].worst_fitness >= history[19].mean_fitness);
+        assert!(history[19].time_in_ms >= history[0].time_in_ms);
+
+        simulation.simulation_result.save_history_csv(&path).unwrap();
+        let mut content = String::new();
+        File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content.lines().count(), 21);
+    }
+
+    #[test]
+    fn migration1() {
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .add_population(make_population(2, IndividualTest1 { value: 50 }))
+            .add_population(make_population(3, IndividualTest1 { value: 100 }))
+            .migration_topology(MigrationTopology::Ring)
+            .share_every(5)
+            .finalize().unwrap();
+
+        simulation.run();
+
+        for population in &simulation.habitat {
+            assert_eq!(population.num_of_migrations, 2);
+        }
+        // The fittest individual of the second population has migrated to the third one and
+        // from there to the first one.
+        assert!(simulation.habitat[2].population.iter().any(|wrapper| wrapper.id == 2));
+        assert!(simulation.habitat[0].population.iter().any(|wrapper| wrapper.id == 2));
+
+        let result = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .migration_topology(MigrationTopology::Custom(vec![MigrationEdge { source: 0, target: 1, interval: 1 }]))
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::MigrationTopologyInvalid, _)) => {}
+            _ => panic!("invalid migration edge not detected"),
+        }
+    }
+
+    #[test]
+    fn migration_policy1() {
+        // The emigrants leave their population, the immigrants take their places.
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .add_population(make_population(2, IndividualTest1 { value: 50 }))
+            .migration_topology(MigrationTopology::FullyConnected)
+            .migration_policy(MigrationPolicy { num_of_migrants: 2, move_migrants: true, .. MigrationPolicy::default() })
+            .share_every(5)
+            .finalize().unwrap();
+
+        simulation.run();
+
+        for population in &simulation.habitat {
+            assert_eq!(population.num_of_migrations, 4);
+            assert_eq!(population.population.len(), 4);
+        }
+        assert!(simulation.habitat[0].population.iter().any(|wrapper| wrapper.id == 2));
+
+        let result = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(10)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .migration_policy(MigrationPolicy { num_of_migrants: 0, .. MigrationPolicy::default() })
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::MigrationPolicyInvalid, _)) => {}
+            _ => panic!("invalid migration policy not detected"),
+        }
+    }
+
+    #[test]
+    fn asynchronous1() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(20)
+            .add_population(make_population(1, IndividualTest1 { value: 100 }))
+            .add_population(make_population(2, IndividualTest1 { value: 100 }))
+            .add_population(make_population(3, IndividualTest1 { value: 100 }))
+            .migration_topology(MigrationTopology::Ring)
+            .share_every(5)
+            .record_history()
+            .observer(TestObserver { events: events.clone() })
+            .asynchronous()
+            .finalize().unwrap();
+
+        simulation.run();
+
+        // The populations are returned in the original order.
+        let ids: Vec<u32> = simulation.habitat.iter().map(|population| population.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let iteration_counter = simulation.simulation_result.iteration_counter;
+        assert!(iteration_counter >= 20);
+        assert!(simulation.simulation_result.fittest[0].fitness <= 80);
+        assert!(simulation.simulation_result.num_of_evaluations >= 12 + 60);
+        assert!(simulation.simulation_result.history.len() >= 60);
+        assert_eq!(events.lock().unwrap().iter().filter(|event| event.starts_with("iteration")).count(),
+            iteration_counter as usize);
+
+        // The iterations after the stop are counted as well.
+        assert_eq!(simulation.simulation_result.num_of_evaluations,
+            simulation.habitat.iter().map(|population| population.num_of_evaluations).sum::<u64>());
+        assert!(simulation.habitat.iter().flat_map(|population| &population.population)
+            .all(|wrapper| wrapper.fitness >= simulation.simulation_result.fittest[0].fitness));
+
+        // A finished simulation does not continue.
+        simulation.run();
+        assert_eq!(simulation.simulation_result.iteration_counter, iteration_counter);
+    }
+
+    #[test]
+    fn asynchronous2() {
+        let mut simulation = SimulationBuilder::<IndividualTest4>::new()
+            .iterations(20)
+            .add_population(make_population(1, IndividualTest4 { value: 100, found: 0 }))
+            .add_population(make_population(2, IndividualTest4 { value: 100, found: 0 }))
+            .asynchronous()
+            .finalize().unwrap();
+
+        simulation.run();
+
+        // `new_fittest_found` is called on the individual in the population, not on the copy.
+        assert!(simulation.habitat.iter().flat_map(|population| &population.population)
+            .any(|wrapper| wrapper.individual.found > 0));
+
+        let builder = || SimulationBuilder::<IndividualTest4>::new()
+            .iterations(20)
+            .add_population(make_population(1, IndividualTest4 { value: 100, found: 0 }))
+            .asynchronous();
+        let path = env::temp_dir().join("darwin_rs_asynchronous2.json");
+
+        // Saving the checkpoint only at the end is fine.
+        assert!(builder().checkpoint(&path, 0).finalize().is_ok());
+
+        let results = vec![
+            builder().checkpoint(&path, 5).finalize(),
+            builder().iterations_per_epoch(5).finalize(),
+            builder().executor(Arc::new(Sequential)).finalize(),
+            builder().scheduling(Scheduling::PerIndividual).finalize(),
+        ];
+        for result in results {
+            match result {
+                Err(simulation_builder::Error(simulation_builder::ErrorKind::AsynchronousOptionInvalid(_), _)) => {}
+                _ => panic!("invalid option not detected"),
+            }
+        }
+
+        assert!(builder().finalize().unwrap().spawn().is_err());
+    }
+
+    #[test]
+    fn epoch1() {
+        let population = make_population(1, IndividualTest1 { value: 100 });
+
+        let mut simulation = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(12)
+            .iterations_per_epoch(5)
+            .add_population(population)
+            .record_history()
+            .finalize().unwrap();
+
+        let progress = simulation.step();
+        assert_eq!(progress.iteration, 5);
+        assert_eq!(progress.fitness, 95);
+
+        simulation.run();
+        assert_eq!(simulation.simulation_result.iteration_counter, 15);
+        assert_eq!(simulation.simulation_result.fittest[0].fitness, 85);
+        assert_eq!(simulation.simulation_result.last_improvement, 15);
+        assert_eq!(simulation.simulation_result.history.len(), 3);
+        assert_eq!(simulation.simulation_result.history[0].iteration, 5);
+        assert_eq!(simulation.simulation_result.history[2].iteration, 15);
+
+        let result = SimulationBuilder::<IndividualTest1>::new()
+            .iterations(12)
+            .iterations_per_epoch(0)
+            .finalize();
+        match result {
+            Err(simulation_builder::Error(simulation_builder::ErrorKind::IterationsPerEpochInvalid, _)) => {}
+            _ => panic!("invalid epoch not detected"),
+        }
+    }
+
+    #[test]
+    fn interval_reached1() {
+        assert!(interval_reached(10, 1, 5));
+        assert!(!interval_reached(11, 1, 5));
+        assert!(interval_reached(12, 4, 5));
+        assert!(!interval_reached(14, 4, 5));
+        assert!(interval_reached(3, 5, 2));
+    }
+
+    /// Reports each iteration and waits in iteration 5 until the test lets it proceed.
+    struct PauseObserver {
+        iterations: mpsc::Sender<u32>,
+        proceed: mpsc::Receiver<()>,
+    }
+
+    impl Observer<IndividualTest2> for PauseObserver {
+        fn on_iteration(&mut self, _result: &SimulationResult<IndividualTest2>, iteration: u32) {
+            let _ = self.iterations.send(iteration);
+            if iteration == 5 {
+                let _ = self.proceed.recv();
+            }
+        }
+    }
+
+    #[test]
+    fn spawn1() {
+        let population = make_population(1, IndividualTest2 { x: 10.0, objectives: Vec::new() });
+        let (iteration_sender, iterations) = mpsc::channel();
+        let (proceed, proceed_receiver) = mpsc::channel();
+
+        // This fitness can never be reached.
+        let handle = SimulationBuilder::<IndividualTest2>::new()
+            .fitness(-1.0)
+            .threads(1)
+            .add_population(population)
+            .observer(PauseObserver { iterations: iteration_sender, proceed: proceed_receiver })
+            .finalize().unwrap()
+            .spawn().unwrap();
+
+        // The simulation waits in iteration 5 until it has been paused.
+        while iterations.recv_timeout(Duration::from_secs(10)).unwrap() < 5 {}
+        handle.pause();
+        proceed.send(()).unwrap();
+
+        while handle.progress().map_or(true, |progress| progress.iteration < 5) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(iterations.recv_timeout(Duration::from_millis(50)).is_err());
+        assert_eq!(handle.progress().unwrap().iteration, 5);
+        assert!(handle.fittest().is_some());
+        assert!(!handle.is_finished());
+
+        handle.resume();
+        assert_eq!(iterations.recv_timeout(Duration::from_secs(10)).unwrap(), 6);
+        handle.stop();
+        let result = handle.join();
 
+        assert!(result.iteration_counter >= 6);
     }
 }
@@ -13,11 +13,20 @@
 //!
 //!
 
-use std;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use simulation::{Simulation, SimulationType, SimulationResult};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use simulation::{self, Simulation, SimulationType, SimulationResult, Checkpoint, Direction, Scheduling};
+use executor::Executor;
 use individual::{Individual};
-use population::Population;
+use migration::{MigrationTopology, MigrationPolicy};
+use observer::Observer;
+use population::{Population, TieBreak};
+use random::DarwinRng;
+use selection::Selection;
 
 /// This is a helper struct in order to build (configure) a valid simulation.
 /// See builder pattern: https://en.wikipedia.org/wiki/Builder_pattern
@@ -26,11 +35,43 @@ use population::Population;
 pub struct SimulationBuilder<T: Individual + Send + Sync> {
     /// The actual simulation.
     simulation: Simulation<T>,
+    /// The user defined strategies (population id, name) that could not be restored from the
+    /// checkpoint and have not been set again yet.
+    unrestored: Vec<(u32, &'static str)>,
+    /// True if the user defined stop criterion could not be restored from the checkpoint and
+    /// has not been set again yet.
+    unrestored_termination: bool,
+    /// The migration topology, migration interval and number of populations of a simulation
+    /// that has been restored from a checkpoint. Its migration edges are kept if these have not
+    /// been changed.
+    restored_topology: Option<(MigrationTopology, u32, usize)>,
 }
 
 error_chain! {
+    links {
+        Simulation(simulation::Error, simulation::ErrorKind);
+    }
+
     errors {
         EndIterationTooLow
+        EmptyTermination
+        MigrationTopologyInvalid
+        MigrationPolicyInvalid
+        IterationsPerEpochInvalid
+        AsynchronousOptionInvalid(option: &'static str) {
+            description("option not supported by an asynchronous simulation")
+            display("option not supported by an asynchronous simulation: {}", option)
+        }
+        StrategyNotRestored(strategy: String) {
+            description("user defined strategy not restored from checkpoint")
+            display("user defined strategy not restored from checkpoint: {}", strategy)
+        }
+    }
+}
+
+impl<T: Individual + Send + Sync> Default for SimulationBuilder<T> {
+    fn default() -> SimulationBuilder<T> {
+        SimulationBuilder::new()
     }
 }
 
@@ -42,22 +83,45 @@ impl<T: Individual + Send + Sync> SimulationBuilder<T> {
         SimulationBuilder {
             simulation: Simulation {
                 type_of_simulation: SimulationType::EndIteration(10),
+                direction: Direction::Minimize,
+                multi_objective: false,
+                pareto_front_size: 100,
+                seed: None,
                 num_of_threads: 2,
+                scheduling: Scheduling::Automatic,
                 habitat: Vec::new(),
                 total_time_in_ms: 0.0,
                 simulation_result: SimulationResult {
-                    improvement_factor: std::f64::MAX,
-                    original_fitness: std::f64::MAX,
+                    improvement_factor: f64::MAX,
+                    original_fitness: Default::default(),
                     fittest: Vec::new(),
-                    iteration_counter: 0
+                    pareto_front: Vec::new(),
+                    iteration_counter: 0,
+                    num_of_failures: 0,
+                    num_of_evaluations: 0,
+                    last_improvement: 0,
+                    history: Vec::new(),
                 },
                 share_fittest: false,
+                migration_topology: MigrationTopology::Global,
+                migration_edges: Vec::new(),
+                migration_policy: MigrationPolicy::default(),
+                migration_rng: DarwinRng::from_entropy(),
                 num_of_global_fittest: 10,
                 output_every: 10,
                 output_every_counter: 0,
                 share_every: 10,
-                share_counter: 0
+                share_counter: 0,
+                record_history: false,
+                iterations_per_epoch: 1,
+                asynchronous: false,
+                checkpoint: None,
+                observers: Vec::new(),
+                executor: None,
             },
+            unrestored: Vec::new(),
+            unrestored_termination: false,
+            restored_topology: None,
         }
     }
 
@@ -65,6 +129,7 @@ impl<T: Individual + Send + Sync> SimulationBuilder<T> {
     /// type to `EndIteration`. (Only usefull in combination with `EndIteration`).
     pub fn iterations(mut self, iterations: u32) -> SimulationBuilder<T> {
         self.simulation.type_of_simulation = SimulationType::EndIteration(iterations);
+        self.unrestored_termination = false;
         self
     }
 
@@ -72,22 +137,129 @@ impl<T: Individual + Send + Sync> SimulationBuilder<T> {
     /// type to `EndFactor`. (Only usefull in combination with `EndFactor`).
     pub fn factor(mut self, factor: f64) -> SimulationBuilder<T> {
         self.simulation.type_of_simulation = SimulationType::EndFactor(factor);
+        self.unrestored_termination = false;
         self
     }
 
     /// Set the minimum fitness stop criteria for the simulation and thus sets the simulation
     /// type to `EndFitness`. (Only usefull in combination with `EndFactor`).
-    pub fn fitness(mut self, fitness: f64) -> SimulationBuilder<T> {
+    pub fn fitness(mut self, fitness: T::Fitness) -> SimulationBuilder<T> {
         self.simulation.type_of_simulation = SimulationType::EndFitness(fitness);
+        self.unrestored_termination = false;
+        self
+    }
+
+    /// Set any stop criteria for the simulation, including the combinations `AnyOf` and `AllOf`
+    /// and user defined criteria (see `SimulationType`). A criterion like `EndFitness` should be
+    /// combined with a limit, so that the simulation can not run forever:
+    /// `AnyOf(vec![EndFitness(0), EndTime(Duration::from_secs(600))])`.
+    pub fn termination(mut self, termination: SimulationType<T::Fitness>) -> SimulationBuilder<T> {
+        self.simulation.type_of_simulation = termination;
+        self.unrestored_termination = false;
+        self
+    }
+
+    /// Set the optimization direction: is a lower (`Minimize`) or a higher (`Maximize`)
+    /// fitness better ? This applies to all populations. Default is `Minimize`.
+    pub fn direction(mut self, direction: Direction) -> SimulationBuilder<T> {
+        self.simulation.direction = direction;
+        self
+    }
+
+    /// If this option is enabled (default: off), then the individuals are sorted by the Pareto
+    /// rank and crowding distance of their objectives (NSGA-II) instead of their fitness.
+    /// The objectives are provided by the `objectives` method of the `Individual` trait.
+    /// The non-dominated individuals are collected in the `pareto_front` of the simulation result.
+    pub fn multi_objective(mut self) -> SimulationBuilder<T> {
+        self.simulation.multi_objective = true;
+        self
+    }
+
+    /// The maximum number of individuals in the Pareto front of the simulation result.
+    /// Only useful in combination with `multi_objective`. Default: 100
+    pub fn pareto_front_size(mut self, pareto_front_size: usize) -> SimulationBuilder<T> {
+        self.simulation.pareto_front_size = pareto_front_size;
+        self
+    }
+
+    /// Sets the master seed for the random number generators of all populations.
+    /// With a seed the simulation can be reproduced exactly, regardless of the number of threads.
+    /// Without a seed each population gets a random seed.
+    pub fn seed(mut self, seed: u64) -> SimulationBuilder<T> {
+        self.simulation.seed = Some(seed);
+        self
+    }
+
+    /// Registers an observer that gets notified about the events of the simulation:
+    /// iterations, new fittest individuals, population resets and migrations.
+    /// Several observers can be registered.
+    pub fn observer<O: Observer<T> + 'static>(mut self, observer: O) -> SimulationBuilder<T> {
+        self.simulation.observers.push(Box::new(observer));
+        self
+    }
+
+    /// If this option is enabled (default: off), the statistics of each population (best, mean,
+    /// worst fitness, standard deviation, number of resets and migrations and the run time) are
+    /// recorded after each iteration in the `history` of the simulation result. With
+    /// `iterations_per_epoch` they are only recorded after each epoch, so there is one entry per
+    /// population and epoch.
+    /// Use `save_history_csv` or `save_history_json_lines` to export it.
+    pub fn record_history(mut self) -> SimulationBuilder<T> {
+        self.simulation.record_history = true;
         self
     }
 
     /// Sets the number of threads in order to speed up the simulation.
+    /// This is ignored if an `executor` is given.
     pub fn threads(mut self, threads: usize) -> SimulationBuilder<T> {
         self.simulation.num_of_threads = threads;
         self
     }
 
+    /// Sets the executor that runs the populations, for example `Sequential` for debugging or
+    /// a `SharedPool` that is shared by several simulations, see `Executor`.
+    /// An `asynchronous` simulation can not use it. Default: a new `JobstealPool` with the
+    /// number of threads given by `threads`.
+    pub fn executor(mut self, executor: Arc<dyn Executor>) -> SimulationBuilder<T> {
+        self.simulation.executor = Some(executor);
+        self
+    }
+
+    /// Sets how the work of one iteration is distributed over the threads, see `Scheduling`.
+    /// Use `PerIndividual` if there are only a few populations with an expensive fitness
+    /// calculation. Default: `Automatic`
+    pub fn scheduling(mut self, scheduling: Scheduling) -> SimulationBuilder<T> {
+        self.simulation.scheduling = scheduling;
+        self
+    }
+
+    /// Sets the number of iterations each population runs before the results are updated and
+    /// the individuals migrate (an epoch). This reduces the overhead of the thread pool if the
+    /// fitness calculation is cheap. The iteration counter is incremented by this number after
+    /// each epoch, so the simulation may run up to `iterations_per_epoch - 1` iterations longer
+    /// than the stop condition. If a migration or checkpoint interval is reached within an epoch,
+    /// the individuals migrate or the checkpoint is saved at the end of the epoch. The history
+    /// (see `record_history`) has one entry per population and epoch.
+    /// Must be > 0, default: 1
+    pub fn iterations_per_epoch(mut self, iterations_per_epoch: u32) -> SimulationBuilder<T> {
+        self.simulation.iterations_per_epoch = iterations_per_epoch;
+        self
+    }
+
+    /// If this option is enabled (default: off), `run` runs each population on its own thread
+    /// and the populations do not wait for each other after each iteration. This is faster if
+    /// the fitness calculation takes a different amount of time in each population.
+    /// The migrants are sent through channels and arrive at the next iteration of the target
+    /// population. The iteration counter is incremented whenever each population has finished
+    /// one iteration on average. The number of threads is the number of populations.
+    /// `iterations_per_epoch`, `executor`, `scheduling` and a checkpoint interval other than 0
+    /// (only save at the end) can not be used, `finalize` returns an error. `step` and `step_n`
+    /// are not affected, `spawn` returns an error.
+    pub fn asynchronous(mut self) -> SimulationBuilder<T> {
+        self.simulation.asynchronous = true;
+        self
+    }
+
     /// Add a population to the simulation.
     pub fn add_population(mut self, population: Population<T>) -> SimulationBuilder<T> {
         self.simulation.habitat.push(population);
@@ -103,12 +275,30 @@ impl<T: Individual + Send + Sync> SimulationBuilder<T> {
     }
 
     /// If this option is enabled (default: off), then the fittest individual of all populations
-    /// is shared between all populations.
+    /// is shared between all populations. It replaces the least fit individual of each
+    /// population (see `migration_policy`), up to version 0.4 it replaced the first one.
     pub fn share_fittest(mut self) -> SimulationBuilder<T> {
         self.simulation.share_fittest = true;
         self
     }
 
+    /// Sets the topology for the migration of individuals between the populations and enables
+    /// `share_fittest`. Default: `Global`. The migration interval of the edges is `share_every`,
+    /// except for `Custom` topologies.
+    pub fn migration_topology(mut self, migration_topology: MigrationTopology) -> SimulationBuilder<T> {
+        self.simulation.migration_topology = migration_topology;
+        self.simulation.share_fittest = true;
+        self
+    }
+
+    /// Sets the migration policy for all populations: how many individuals migrate along each
+    /// edge of the migration topology, how they are chosen, whom they replace and if they are
+    /// copied or moved. Default: the fittest individual is copied and replaces the least fit one.
+    pub fn migration_policy(mut self, migration_policy: MigrationPolicy) -> SimulationBuilder<T> {
+        self.simulation.migration_policy = migration_policy;
+        self
+    }
+
     /// How many global fittest should be kept ? (The size of the "high score list")
     pub fn num_of_global_fittest(mut self, num_of_global_fittest: usize) -> SimulationBuilder<T> {
         self.simulation.num_of_global_fittest = num_of_global_fittest;
@@ -131,12 +321,164 @@ impl<T: Individual + Send + Sync> SimulationBuilder<T> {
 
     /// This checks the configuration of the simulation and returns an error or Ok if no errors
     /// where found.
-    pub fn finalize(self) -> Result<Simulation<T>> {
-        match self.simulation {
-            Simulation { type_of_simulation: SimulationType::EndIteration(0...9), .. } => {
-                Err(ErrorKind::EndIterationTooLow.into())
+    pub fn finalize(mut self) -> Result<Simulation<T>> {
+        if self.unrestored_termination {
+            return Err(ErrorKind::StrategyNotRestored("termination".to_string()).into());
+        }
+        if let Some(&(population_id, strategy)) = self.unrestored.first() {
+            return Err(ErrorKind::StrategyNotRestored(format!("population {}, {}", population_id, strategy)).into());
+        }
+
+        for population in &mut self.simulation.habitat {
+            population.direction = self.simulation.direction;
+            population.multi_objective = self.simulation.multi_objective;
+        }
+
+        // Derive the random number generators from the master seed. A simulation that has been
+        // restored from a checkpoint keeps the state of its generators.
+        if let Some(seed) = self.simulation.seed {
+            if self.simulation.simulation_result.fittest.is_empty() {
+                for (index, population) in self.simulation.habitat.iter_mut().enumerate() {
+                    population.rng = DarwinRng::from_seed(DarwinRng::derive_seed(seed, index as u64));
+                }
+            }
+        }
+
+        check_termination(&self.simulation.type_of_simulation)?;
+
+        // Create the edges of the migration topology. The random number generator for the
+        // migration is derived from the master seed as well.
+        let num_of_populations = self.simulation.habitat.len();
+        if let Some(seed) = self.simulation.seed {
+            if self.simulation.simulation_result.fittest.is_empty() {
+                self.simulation.migration_rng = DarwinRng::from_seed(DarwinRng::derive_seed(seed, num_of_populations as u64));
+            }
+        }
+        if let MigrationTopology::RandomNeighbours(0) = self.simulation.migration_topology {
+            return Err(ErrorKind::MigrationTopologyInvalid.into());
+        }
+        if self.simulation.migration_policy.num_of_migrants == 0 {
+            return Err(ErrorKind::MigrationPolicyInvalid.into());
+        }
+        if self.simulation.iterations_per_epoch == 0 {
+            return Err(ErrorKind::IterationsPerEpochInvalid.into());
+        }
+        if self.simulation.asynchronous {
+            check_asynchronous(&self.simulation)?;
+        }
+        let topology = (self.simulation.migration_topology.clone(), self.simulation.share_every, num_of_populations);
+        if self.restored_topology != Some(topology) {
+            self.simulation.migration_edges = self.simulation.migration_topology.edges(
+                num_of_populations, self.simulation.share_every, &mut self.simulation.migration_rng);
+        }
+        if self.simulation.migration_edges.iter().any(|edge| edge.interval == 0 ||
+            edge.source >= num_of_populations || edge.target >= num_of_populations || edge.source == edge.target) {
+            return Err(ErrorKind::MigrationTopologyInvalid.into());
+        }
+
+        Ok(self.simulation)
+    }
+}
+
+/// Checks that an asynchronous simulation does not use any option that only works if the
+/// populations wait for each other after each iteration.
+fn check_asynchronous<T: Individual + Send + Sync>(simulation: &Simulation<T>) -> Result<()> {
+    if simulation.iterations_per_epoch != 1 {
+        Err(ErrorKind::AsynchronousOptionInvalid("iterations_per_epoch").into())
+    } else if simulation.executor.is_some() {
+        Err(ErrorKind::AsynchronousOptionInvalid("executor").into())
+    } else if simulation.scheduling != Scheduling::Automatic {
+        Err(ErrorKind::AsynchronousOptionInvalid("scheduling").into())
+    } else if simulation.checkpoint.as_ref().map_or(false, |checkpoint| checkpoint.every > 0) {
+        Err(ErrorKind::AsynchronousOptionInvalid("checkpoint interval").into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks the stop criteria, including the nested ones.
+fn check_termination<F>(termination: &SimulationType<F>) -> Result<()> {
+    match *termination {
+        SimulationType::EndIteration(0..=9) => Err(ErrorKind::EndIterationTooLow.into()),
+        SimulationType::AnyOf(ref criteria) | SimulationType::AllOf(ref criteria) => {
+            if criteria.is_empty() {
+                return Err(ErrorKind::EmptyTermination.into());
             }
-            _ => Ok(self.simulation),
+            for criterion in criteria {
+                check_termination(criterion)?;
+            }
+            Ok(())
         }
+        _ => Ok(()),
+    }
+}
+
+impl<T: Individual + Send + Sync + Clone + Serialize> SimulationBuilder<T> {
+    /// Save the simulation automatically to the given checkpoint file every nth iteration and
+    /// when the simulation has finished. If the machine goes down the simulation can be
+    /// restored from that file using `from_checkpoint`. If `every` is 0, it is only saved at
+    /// the end.
+    pub fn checkpoint<P: AsRef<Path>>(mut self, path: P, every: u32) -> SimulationBuilder<T> where T::Fitness: Serialize {
+        self.simulation.checkpoint = Some(Checkpoint {
+            path: PathBuf::from(path.as_ref()),
+            every,
+            save: |simulation, path| simulation.save_checkpoint(path),
+        });
+        self
+    }
+}
+
+impl<T: Individual + Send + Sync + Clone + DeserializeOwned> SimulationBuilder<T> {
+    /// Restore a simulation from a checkpoint file that has been written with `save_checkpoint`
+    /// or automatically with `checkpoint`. All the populations, the results and the iteration
+    /// counter are restored, so calling `run` continues the simulation where it stopped.
+    /// The other methods of the builder can be used to change the configuration, for example
+    /// to increase the number of iterations.
+    /// User defined selection strategies and tie breaks can not be saved, they have to be set
+    /// again with `restore_selection`, `restore_parent_selection` and `restore_tie_break`.
+    /// A user defined stop criterion has to be set again with `termination`.
+    /// Otherwise `finalize` returns an error.
+    pub fn from_checkpoint<P: AsRef<Path>>(path: P) -> Result<SimulationBuilder<T>> where T::Fitness: DeserializeOwned {
+        let simulation = Simulation::read_checkpoint(path)?;
+        let unrestored = simulation.unrestored_strategies();
+        let unrestored_termination = simulation.type_of_simulation.is_custom();
+        let restored_topology = Some((simulation.migration_topology.clone(), simulation.share_every, simulation.habitat.len()));
+
+        Ok(SimulationBuilder {
+            simulation,
+            unrestored,
+            unrestored_termination,
+            restored_topology,
+        })
+    }
+
+    /// Sets the user defined survivor selection strategy of the population with the given id
+    /// again, after the simulation has been restored with `from_checkpoint`.
+    pub fn restore_selection<S: Selection + 'static>(self, population_id: u32, selection: S) -> SimulationBuilder<T> {
+        let selection: Arc<dyn Selection> = Arc::new(selection);
+        self.restore(population_id, "selection", |population| population.selection = selection.clone())
+    }
+
+    /// Sets the user defined parent selection strategy of the population with the given id
+    /// again, after the simulation has been restored with `from_checkpoint`.
+    pub fn restore_parent_selection<S: Selection + 'static>(self, population_id: u32, selection: S) -> SimulationBuilder<T> {
+        let selection: Arc<dyn Selection> = Arc::new(selection);
+        self.restore(population_id, "parent_selection", |population| population.parent_selection = selection.clone())
+    }
+
+    /// Sets the custom tie break of the population with the given id again, after the
+    /// simulation has been restored with `from_checkpoint`.
+    pub fn restore_tie_break(self, population_id: u32, tie_break: TieBreak<T>) -> SimulationBuilder<T> {
+        self.restore(population_id, "tie_break", |population| population.tie_break = tie_break.clone())
+    }
+
+    /// Sets a strategy of all populations with the given id and marks it as restored.
+    fn restore<F: FnMut(&mut Population<T>)>(mut self, population_id: u32, strategy: &'static str, mut set: F) -> SimulationBuilder<T> {
+        for population in self.simulation.habitat.iter_mut().filter(|population| population.id == population_id) {
+            set(population);
+        }
+
+        self.unrestored.retain(|&(id, name)| id != population_id || name != strategy);
+        self
     }
 }
@@ -0,0 +1,137 @@
+//! This module defines the `SimulationHandle` that controls a simulation running on a
+//! background thread.
+
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread::{self, JoinHandle};
+
+use individual::{Individual, IndividualWrapper};
+use simulation::{Simulation, SimulationResult, SimulationProgress};
+
+/// What the background thread should do next.
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Command {
+    Run,
+    Pause,
+    Stop,
+}
+
+/// The state that is shared between the handle and the background thread.
+struct Shared<T: Individual> {
+    command: Command,
+    progress: Option<SimulationProgress<T::Fitness>>,
+    fittest: Option<IndividualWrapper<T>>,
+}
+
+/// The `SimulationHandle` type. Controls a simulation that runs on a background thread.
+/// Use the `spawn` method of the `Simulation` to create it.
+/// The progress and the fittest individual are updated after each iteration.
+/// If the handle is dropped without calling `join`, the simulation keeps running until it is
+/// finished.
+pub struct SimulationHandle<T: Individual + Send + Sync> {
+    shared: Arc<(Mutex<Shared<T>>, Condvar)>,
+    thread: JoinHandle<Simulation<T>>,
+}
+
+impl<T: Individual + Send + Sync + Clone + 'static> SimulationHandle<T> {
+    /// Starts the given simulation on a new thread. This is called by `Simulation::spawn`.
+    pub fn new(mut simulation: Simulation<T>) -> SimulationHandle<T> {
+        let shared = Arc::new((Mutex::new(Shared {
+            command: Command::Run,
+            progress: None,
+            fittest: None,
+        }), Condvar::new()));
+
+        let thread_shared = shared.clone();
+
+        let thread = thread::spawn(move || {
+            let (ref lock, ref condvar) = *thread_shared;
+
+            simulation.init();
+
+            loop {
+                {
+                    let mut state = lock.lock().unwrap();
+                    while state.command == Command::Pause {
+                        state = condvar.wait(state).unwrap();
+                    }
+                    if state.command == Command::Stop {
+                        break;
+                    }
+                }
+
+                if simulation.is_finished() {
+                    break;
+                }
+
+                let progress = simulation.step();
+
+                let mut state = lock.lock().unwrap();
+                state.progress = Some(progress);
+                state.fittest = Some(simulation.simulation_result.fittest[0].clone());
+            }
+
+            simulation.save_automatic_checkpoint(true);
+            simulation
+        });
+
+        SimulationHandle {
+            shared,
+            thread,
+        }
+    }
+
+    /// Returns the progress after the last iteration, or `None` if no iteration has finished yet.
+    pub fn progress(&self) -> Option<SimulationProgress<T::Fitness>> {
+        self.shared.0.lock().unwrap().progress.clone()
+    }
+
+    /// Returns the fittest individual found so far, or `None` if no iteration has finished yet.
+    pub fn fittest(&self) -> Option<IndividualWrapper<T>> {
+        self.shared.0.lock().unwrap().fittest.clone()
+    }
+
+    /// Pauses the simulation after the current iteration.
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    /// Resumes a paused simulation.
+    pub fn resume(&self) {
+        self.send(Command::Run);
+    }
+
+    /// Requests a graceful stop: the simulation stops after the current iteration (even if it
+    /// is paused) and saves the final checkpoint, if enabled.
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    /// Returns true if the background thread has finished: the stop condition has been reached
+    /// or a stop has been requested.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits until the simulation has finished and returns its result.
+    /// If the simulation has not reached its stop condition, call `stop` first.
+    pub fn join(self) -> SimulationResult<T> {
+        self.join_simulation().simulation_result
+    }
+
+    /// Waits until the simulation has finished and returns the whole simulation, so it can
+    /// be saved or continued later.
+    pub fn join_simulation(self) -> Simulation<T> {
+        self.thread.join().expect("simulation thread panicked")
+    }
+
+    fn send(&self, command: Command) {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut state = lock.lock().unwrap();
+
+        // A stop request can not be undone.
+        if state.command != Command::Stop {
+            state.command = command;
+        }
+        condvar.notify_all();
+    }
+}
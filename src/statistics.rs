@@ -0,0 +1,145 @@
+//! This module defines the statistics that are recorded for each iteration and population and
+//! their export to CSV and JSON Lines.
+
+use std::io::{self, Write};
+
+use serde_json;
+
+use fitness::Fitness;
+use individual::Individual;
+use population::Population;
+
+/// The `IterationStatistics` type. The statistics of one population after one iteration.
+/// They are collected in the `history` of the `SimulationResult` if `record_history` is enabled
+/// in the `SimulationBuilder`. If `iterations_per_epoch` is set, they are only recorded after
+/// each epoch, for its last iteration.
+/// The fitness values are converted with `to_f64`, failed individuals are ignored.
+/// If there is no valid individual, the fitness values are NaN, which is written as "NaN" to
+/// JSON.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct IterationStatistics {
+    /// The iteration, starting with 1. With epochs this is a multiple of `iterations_per_epoch`.
+    pub iteration: u32,
+    /// The id of the population.
+    pub population_id: u32,
+    /// The fitness of the fittest individual of the population.
+    #[serde(with = "::fitness::checkpoint_f64")]
+    pub best_fitness: f64,
+    /// The mean fitness of the population.
+    #[serde(with = "::fitness::checkpoint_f64")]
+    pub mean_fitness: f64,
+    /// The fitness of the least fit individual of the population.
+    #[serde(with = "::fitness::checkpoint_f64")]
+    pub worst_fitness: f64,
+    /// The standard deviation of the fitness of the population.
+    #[serde(with = "::fitness::checkpoint_f64")]
+    pub std_dev_fitness: f64,
+    /// How often the population has been reset so far.
+    pub num_of_resets: u64,
+    /// How many individuals have migrated into the population so far.
+    pub num_of_migrations: u64,
+    /// The total run time of the simulation so far.
+    pub time_in_ms: f64,
+}
+
+/// The header line of the CSV export.
+const CSV_HEADER: &str = "iteration,population_id,best_fitness,mean_fitness,worst_fitness,std_dev_fitness,num_of_resets,num_of_migrations,time_in_ms";
+
+impl IterationStatistics {
+    /// Calculates the statistics of the given population.
+    pub fn new<T: Individual + Send + Sync + Clone>(population: &Population<T>, iteration: u32, time_in_ms: f64) -> IterationStatistics {
+        let fitness: Vec<f64> = population.population.iter()
+            .filter(|wrapper| !wrapper.has_failed())
+            .map(|wrapper| wrapper.fitness.to_f64())
+            .collect();
+
+        let (best_fitness, worst_fitness) = fitness.iter().fold((f64::NAN, f64::NAN), |(best, worst), &value| {
+            let best = if best.is_nan() || population.direction.is_better(&value, &best) { value } else { best };
+            let worst = if worst.is_nan() || population.direction.is_better(&worst, &value) { value } else { worst };
+            (best, worst)
+        });
+
+        let length = fitness.len() as f64;
+        let mean_fitness = fitness.iter().sum::<f64>() / length;
+        let std_dev_fitness = (fitness.iter().map(|value| (value - mean_fitness).powi(2)).sum::<f64>() / length).sqrt();
+
+        IterationStatistics {
+            iteration,
+            population_id: population.id,
+            best_fitness,
+            mean_fitness,
+            worst_fitness,
+            std_dev_fitness,
+            num_of_resets: population.num_of_resets,
+            num_of_migrations: population.num_of_migrations,
+            time_in_ms,
+        }
+    }
+}
+
+/// Writes the history as CSV with a header line.
+pub fn write_csv<W: Write>(history: &[IterationStatistics], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+
+    for statistics in history {
+        writeln!(writer, "{},{},{},{},{},{},{},{},{}", statistics.iteration, statistics.population_id,
+            statistics.best_fitness, statistics.mean_fitness, statistics.worst_fitness,
+            statistics.std_dev_fitness, statistics.num_of_resets, statistics.num_of_migrations,
+            statistics.time_in_ms)?;
+    }
+
+    writer.flush()
+}
+
+/// Writes the history as JSON Lines: one JSON object per line.
+pub fn write_json_lines<W: Write>(history: &[IterationStatistics], mut writer: W) -> io::Result<()> {
+    for statistics in history {
+        serde_json::to_writer(&mut writer, statistics)?;
+        writeln!(writer)?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IterationStatistics, write_csv, write_json_lines};
+
+    fn make_history() -> Vec<IterationStatistics> {
+        vec![IterationStatistics {
+            iteration: 1,
+            population_id: 2,
+            best_fitness: 1.0,
+            mean_fitness: 2.0,
+            worst_fitness: 3.0,
+            std_dev_fitness: 0.5,
+            num_of_resets: 0,
+            num_of_migrations: 1,
+            time_in_ms: 10.0,
+        }]
+    }
+
+    #[test]
+    fn write_csv1() {
+        let mut output = Vec::new();
+        write_csv(&make_history(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("iteration,population_id,best_fitness"));
+        assert_eq!(lines[1], "1,2,1,2,3,0.5,0,1,10");
+    }
+
+    #[test]
+    fn write_json_lines1() {
+        let history = make_history();
+        let mut output = Vec::new();
+        write_json_lines(&history, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(::serde_json::from_str::<IterationStatistics>(lines[0]).unwrap(), history[0]);
+    }
+}